@@ -95,6 +95,44 @@ mod parsing_tests {
     }
 }
 
+pub mod random {
+    /// A small deterministic generator, so anything made from it can be made again from its
+    /// seed. Not for anything that needs to be unpredictable.
+    pub struct Random(u64);
+
+    impl Random {
+        pub fn new(seed: u64) -> Self {
+            Random(seed)
+        }
+
+        /// The next number in `0..below`.
+        pub fn next(&mut self, below: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) % below
+        }
+    }
+}
+
+#[cfg(test)]
+mod random_tests {
+    use crate::random::Random;
+
+    #[test]
+    fn repeats_from_the_same_seed() {
+        let numbers = |seed| {
+            let mut random = Random::new(seed);
+            (0..20).map(|_| random.next(100)).collect::<Vec<u64>>()
+        };
+
+        assert_eq!(numbers(2023), numbers(2023));
+        assert_ne!(numbers(2023), numbers(2024));
+        assert!(numbers(2023).iter().all(|n| *n < 100));
+    }
+}
+
 mod geometry {
     #[derive(PartialEq, Eq, Hash, Clone, Debug)]
     pub struct Coordinate {
@@ -133,3 +171,612 @@ mod geometry_tests {
         assert_eq!(distance, expected);
     }
 }
+
+pub mod render {
+    use crate::core::{Error, Result};
+    use std::{borrow::Cow, collections::HashSet, fs, io::Write, thread, time::Duration};
+
+    const PNG_BACKGROUND: [u8; 3] = [0, 0, 0];
+    const PNG_GLYPH: [u8; 3] = [80, 80, 80];
+    const COLOURS: [Colour; 7] = [
+        Colour::Red,
+        Colour::Green,
+        Colour::Yellow,
+        Colour::Blue,
+        Colour::Magenta,
+        Colour::Cyan,
+        Colour::White,
+    ];
+
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub enum Colour {
+        Red,
+        Green,
+        Yellow,
+        Blue,
+        Magenta,
+        Cyan,
+        White,
+    }
+
+    impl Colour {
+        fn ansi_code(&self) -> u8 {
+            match self {
+                Colour::Red => 31,
+                Colour::Green => 32,
+                Colour::Yellow => 33,
+                Colour::Blue => 34,
+                Colour::Magenta => 35,
+                Colour::Cyan => 36,
+                Colour::White => 37,
+            }
+        }
+
+        fn rgb(&self) -> [u8; 3] {
+            match self {
+                Colour::Red => [220, 50, 47],
+                Colour::Green => [133, 153, 0],
+                Colour::Yellow => [181, 137, 0],
+                Colour::Blue => [38, 139, 210],
+                Colour::Magenta => [211, 54, 130],
+                Colour::Cyan => [42, 161, 152],
+                Colour::White => [238, 232, 213],
+            }
+        }
+
+        fn hex(&self) -> String {
+            let [r, g, b] = self.rgb();
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        }
+    }
+
+    /// A named set of cells to highlight in one colour.
+    #[derive(Debug)]
+    pub struct Layer {
+        pub name: String,
+        pub colour: Colour,
+        cells: HashSet<(usize, usize)>,
+    }
+
+    impl Layer {
+        pub fn new<I>(name: &str, colour: Colour, cells: I) -> Layer
+        where
+            I: IntoIterator<Item = (usize, usize)>,
+        {
+            Layer {
+                name: String::from(name),
+                colour,
+                cells: cells.into_iter().collect(),
+            }
+        }
+
+        pub fn contains(&self, x: usize, y: usize) -> bool {
+            self.cells.contains(&(x, y))
+        }
+    }
+
+    /// A grid of glyphs with layers painted over it. Later layers win where they overlap.
+    #[derive(Debug)]
+    pub struct Scene {
+        width: usize,
+        height: usize,
+        glyphs: Vec<Vec<char>>,
+        layers: Vec<Layer>,
+    }
+
+    impl Scene {
+        pub fn new(glyphs: Vec<Vec<char>>) -> Scene {
+            Scene {
+                width: glyphs.iter().map(|row| row.len()).max().unwrap_or(0),
+                height: glyphs.len(),
+                glyphs,
+                layers: vec![],
+            }
+        }
+
+        pub fn from_lines(lines: &[&str]) -> Scene {
+            Scene::new(lines.iter().map(|l| l.chars().collect()).collect())
+        }
+
+        pub fn with_layer(mut self, layer: Layer) -> Scene {
+            self.layers.push(layer);
+            self
+        }
+
+        pub fn width(&self) -> usize {
+            self.width
+        }
+
+        pub fn height(&self) -> usize {
+            self.height
+        }
+
+        fn glyph(&self, x: usize, y: usize) -> char {
+            self.glyphs[y].get(x).copied().unwrap_or(' ')
+        }
+
+        fn colour(&self, x: usize, y: usize) -> Option<Colour> {
+            self.layers
+                .iter()
+                .rev()
+                .find(|l| l.contains(x, y))
+                .map(|l| l.colour)
+        }
+
+        pub fn to_ansi(&self) -> String {
+            let mut out = String::new();
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let glyph = self.glyph(x, y);
+                    match self.colour(x, y) {
+                        Some(c) => {
+                            out.push_str(&format!("\x1b[{}m{}\x1b[0m", c.ansi_code(), glyph))
+                        }
+                        None => out.push(glyph),
+                    }
+                }
+                out.push('\n');
+            }
+
+            out
+        }
+
+        pub fn to_svg(&self) -> String {
+            let mut out = format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+                self.width * 10,
+                self.height * 10,
+                self.width,
+                self.height
+            );
+            out.push_str("<rect width=\"100%\" height=\"100%\" fill=\"#000000\"/>\n");
+
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    if let Some(c) = self.colour(x, y) {
+                        out.push_str(&format!(
+                            "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"{}\"/>\n",
+                            x,
+                            y,
+                            c.hex()
+                        ));
+                    }
+                }
+            }
+
+            out.push_str("<g font-family=\"monospace\" font-size=\"0.8\" text-anchor=\"middle\" fill=\"#ffffff\">\n");
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let glyph = self.glyph(x, y);
+                    if glyph.is_whitespace() {
+                        continue;
+                    }
+
+                    out.push_str(&format!(
+                        "<text x=\"{}.5\" y=\"{}.75\">{}</text>\n",
+                        x,
+                        y,
+                        escape_xml(glyph)
+                    ));
+                }
+            }
+            out.push_str("</g>\n</svg>\n");
+
+            out
+        }
+
+        /// Encode as a PNG with each cell drawn as a `cell_size` pixel square. Glyphs aren't drawn,
+        /// non-blank cells outside every layer are shaded grey.
+        pub fn to_png(&self, cell_size: usize) -> Result<Vec<u8>> {
+            let width = self.width * cell_size;
+            let height = self.height * cell_size;
+            let pixels = self.to_rgb(cell_size);
+
+            let mut bytes: Vec<u8> = vec![];
+            let mut encoder = png::Encoder::new(&mut bytes, width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+
+            let mut writer = encoder
+                .write_header()
+                .map_err(|e| Error::new(&format!("can't write png header: {}", e)))?;
+            writer
+                .write_image_data(&pixels)
+                .map_err(|e| Error::new(&format!("can't write png data: {}", e)))?;
+            writer
+                .finish()
+                .map_err(|e| Error::new(&format!("can't finish png: {}", e)))?;
+
+            Ok(bytes)
+        }
+
+        fn to_rgb(&self, cell_size: usize) -> Vec<u8> {
+            let palette = palette();
+
+            self.to_indexed(cell_size)
+                .iter()
+                .flat_map(|i| palette[*i as usize * 3..*i as usize * 3 + 3].to_vec())
+                .collect()
+        }
+
+        /// Pixels as indexes into `palette()`.
+        fn to_indexed(&self, cell_size: usize) -> Vec<u8> {
+            let mut pixels: Vec<u8> =
+                Vec::with_capacity(self.width * self.height * cell_size * cell_size);
+            for y in 0..self.height {
+                let row: Vec<u8> = (0..self.width)
+                    .flat_map(|x| {
+                        let index = match self.colour(x, y) {
+                            Some(c) => 2 + COLOURS.iter().position(|p| *p == c).unwrap() as u8,
+                            None if is_blank(self.glyph(x, y)) => 0,
+                            None => 1,
+                        };
+                        [index].repeat(cell_size)
+                    })
+                    .collect();
+
+                for _ in 0..cell_size {
+                    pixels.extend(&row);
+                }
+            }
+
+            pixels
+        }
+
+        /// Write to a file, picking SVG or PNG from the extension.
+        pub fn write(&self, path: &str) -> Result<()> {
+            let bytes = if path.ends_with(".svg") {
+                self.to_svg().into_bytes()
+            } else if path.ends_with(".png") {
+                self.to_png(8)?
+            } else {
+                return Err(Error::new("can only render to .svg or .png"));
+            };
+
+            fs::write(path, bytes).map_err(|_| Error::new("can't write file"))
+        }
+    }
+
+    /// Collects a scene per step of an algorithm, to replay in the terminal or export as an animation.
+    /// A disabled recorder skips building the scenes, so solvers can always take one.
+    #[derive(Debug)]
+    pub struct Recorder {
+        enabled: bool,
+        every: usize,
+        steps: usize,
+        frames: Vec<Scene>,
+    }
+
+    impl Recorder {
+        pub fn new() -> Recorder {
+            Recorder::every(1)
+        }
+
+        /// Only keep every nth step, for algorithms that take too many to store.
+        pub fn every(n: usize) -> Recorder {
+            Recorder {
+                enabled: true,
+                every: n.max(1),
+                steps: 0,
+                frames: vec![],
+            }
+        }
+
+        pub fn disabled() -> Recorder {
+            Recorder {
+                enabled: false,
+                every: 1,
+                steps: 0,
+                frames: vec![],
+            }
+        }
+
+        pub fn record<F>(&mut self, frame: F)
+        where
+            F: FnOnce() -> Scene,
+        {
+            if self.enabled && self.steps.is_multiple_of(self.every) {
+                self.frames.push(frame());
+            }
+            self.steps += 1;
+        }
+
+        pub fn frames(&self) -> &[Scene] {
+            &self.frames
+        }
+
+        /// Play the frames back as ANSI, clearing the screen between each.
+        pub fn replay<W: Write>(&self, out: &mut W, frame_delay: Duration) -> Result<()> {
+            for frame in self.frames.iter() {
+                write!(out, "\x1b[2J\x1b[H{}", frame.to_ansi())
+                    .and_then(|_| out.flush())
+                    .map_err(|_| Error::new("can't write frame"))?;
+                thread::sleep(frame_delay);
+            }
+
+            Ok(())
+        }
+
+        pub fn to_gif(&self, cell_size: usize, frame_delay: Duration) -> Result<Vec<u8>> {
+            let (width, height) = self.frame_size(cell_size)?;
+            let delay = fit_u16(frame_delay.as_millis() / 10, "frame delay in 1/100 s")?;
+
+            let mut bytes: Vec<u8> = vec![];
+            {
+                let mut encoder = gif::Encoder::new(&mut bytes, width, height, &palette())
+                    .map_err(|e| Error::new(&format!("can't write gif header: {}", e)))?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(|e| Error::new(&format!("can't write gif header: {}", e)))?;
+
+                for scene in self.frames.iter() {
+                    let frame = gif::Frame {
+                        width,
+                        height,
+                        delay,
+                        buffer: Cow::Owned(scene.to_indexed(cell_size)),
+                        ..gif::Frame::default()
+                    };
+                    encoder
+                        .write_frame(&frame)
+                        .map_err(|e| Error::new(&format!("can't write gif frame: {}", e)))?;
+                }
+            }
+
+            Ok(bytes)
+        }
+
+        pub fn to_apng(&self, cell_size: usize, frame_delay: Duration) -> Result<Vec<u8>> {
+            let (width, height) = self.frame_size(cell_size)?;
+            let delay = fit_u16(frame_delay.as_millis(), "frame delay in ms")?;
+            let frames = u32::try_from(self.frames.len())
+                .map_err(|_| Error::new("too many frames for an apng"))?;
+
+            let mut bytes: Vec<u8> = vec![];
+            let mut encoder = png::Encoder::new(&mut bytes, width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .set_animated(frames, 0)
+                .and_then(|_| encoder.set_frame_delay(delay, 1000))
+                .map_err(|e| Error::new(&format!("can't write apng header: {}", e)))?;
+
+            let mut writer = encoder
+                .write_header()
+                .map_err(|e| Error::new(&format!("can't write apng header: {}", e)))?;
+            for scene in self.frames.iter() {
+                writer
+                    .write_image_data(&scene.to_rgb(cell_size))
+                    .map_err(|e| Error::new(&format!("can't write apng frame: {}", e)))?;
+            }
+            writer
+                .finish()
+                .map_err(|e| Error::new(&format!("can't finish apng: {}", e)))?;
+
+            Ok(bytes)
+        }
+
+        /// Write to a file, picking GIF or APNG from the extension.
+        pub fn write(&self, path: &str, frame_delay: Duration) -> Result<()> {
+            let bytes = if path.ends_with(".gif") {
+                self.to_gif(8, frame_delay)?
+            } else if path.ends_with(".png") || path.ends_with(".apng") {
+                self.to_apng(8, frame_delay)?
+            } else {
+                return Err(Error::new("can only record to .gif or .png"));
+            };
+
+            fs::write(path, bytes).map_err(|_| Error::new("can't write file"))
+        }
+
+        fn frame_size(&self, cell_size: usize) -> Result<(u16, u16)> {
+            let first = self
+                .frames
+                .first()
+                .ok_or_else(|| Error::new("no frames recorded"))?;
+            if self
+                .frames
+                .iter()
+                .any(|f| f.width != first.width || f.height != first.height)
+            {
+                return Err(Error::new("frames must all be the same size"));
+            }
+
+            let pixels = |cells: usize, what: &str| {
+                let pixels = cells
+                    .checked_mul(cell_size)
+                    .ok_or_else(|| Error::new(&format!("{} overflows", what)))?;
+                fit_u16(pixels as u128, what)
+            };
+
+            Ok((
+                pixels(first.width, "width")?,
+                pixels(first.height, "height")?,
+            ))
+        }
+    }
+
+    /// GIF and APNG store sizes and delays in 16 bits.
+    fn fit_u16(value: u128, what: &str) -> Result<u16> {
+        u16::try_from(value).map_err(|_| {
+            Error::new(&format!(
+                "{} of {} is more than an animation can hold ({})",
+                what,
+                value,
+                u16::MAX
+            ))
+        })
+    }
+
+    impl Default for Recorder {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// Background, then glyph shading, then every colour.
+    fn palette() -> Vec<u8> {
+        let mut palette = vec![];
+        palette.extend(PNG_BACKGROUND);
+        palette.extend(PNG_GLYPH);
+        for colour in COLOURS {
+            palette.extend(colour.rgb());
+        }
+
+        palette
+    }
+
+    fn is_blank(glyph: char) -> bool {
+        glyph == '.' || glyph.is_whitespace()
+    }
+
+    fn escape_xml(glyph: char) -> String {
+        match glyph {
+            '&' => String::from("&amp;"),
+            '<' => String::from("&lt;"),
+            '>' => String::from("&gt;"),
+            '"' => String::from("&quot;"),
+            c => c.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod render_tests {
+    use crate::render::{Colour, Layer, Recorder, Scene};
+    use std::time::Duration;
+
+    fn example() -> Scene {
+        Scene::from_lines(&["#..", ".<.", "..#"])
+            .with_layer(Layer::new("diagonal", Colour::Red, [(0, 0), (1, 1)]))
+            .with_layer(Layer::new("corner", Colour::Green, [(1, 1), (2, 2)]))
+    }
+
+    #[test]
+    fn can_render_to_ansi() {
+        let ansi = example().to_ansi();
+
+        assert_eq!(
+            ansi,
+            "\x1b[31m#\x1b[0m..\n.\x1b[32m<\x1b[0m.\n..\x1b[32m#\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn can_render_to_svg() {
+        let svg = example().to_svg();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"30\" height=\"30\" viewBox=\"0 0 3 3\">"));
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"1\" height=\"1\" fill=\"#dc322f\"/>"));
+        assert!(svg.contains("<rect x=\"1\" y=\"1\" width=\"1\" height=\"1\" fill=\"#859900\"/>"));
+        assert!(svg.contains("<text x=\"1.5\" y=\"1.75\">&lt;</text>"));
+        assert_eq!(svg.matches("<rect x=").count(), 3);
+        assert_eq!(svg.matches("<text").count(), 9);
+    }
+
+    #[test]
+    fn can_render_to_png() {
+        let bytes = example().to_png(4).unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (12, 12));
+        assert_eq!(pixels[0..3], [220, 50, 47]);
+        assert_eq!(pixels[4 * 3..4 * 3 + 3], [0, 0, 0]);
+    }
+
+    fn recording() -> Recorder {
+        let mut recorder = Recorder::new();
+        for step in 0..3 {
+            recorder.record(|| {
+                Scene::from_lines(&["...", "..."]).with_layer(Layer::new(
+                    "front",
+                    Colour::Yellow,
+                    [(step, 0), (step, 1)],
+                ))
+            });
+        }
+
+        recorder
+    }
+
+    #[test]
+    fn disabled_recorder_skips_frames() {
+        let mut recorder = Recorder::disabled();
+
+        recorder.record(|| panic!("shouldn't build the frame"));
+
+        assert!(recorder.frames().is_empty());
+    }
+
+    #[test]
+    fn can_sample_frames() {
+        let mut recorder = Recorder::every(2);
+
+        for _ in 0..5 {
+            recorder.record(|| Scene::from_lines(&["."]));
+        }
+
+        assert_eq!(recorder.frames().len(), 3);
+    }
+
+    #[test]
+    fn can_replay_frames_to_terminal() {
+        let mut out: Vec<u8> = vec![];
+
+        recording().replay(&mut out, Duration::ZERO).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("\x1b[2J\x1b[H").count(), 3);
+        assert!(out.ends_with("..\x1b[33m.\x1b[0m\n..\x1b[33m.\x1b[0m\n"));
+    }
+
+    #[test]
+    fn can_record_to_gif() {
+        let bytes = recording().to_gif(2, Duration::from_millis(100)).unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(bytes.as_slice())
+            .unwrap();
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!((frame.width, frame.height, frame.delay), (6, 4, 10));
+            frames += 1;
+        }
+
+        assert_eq!(frames, 3);
+    }
+
+    #[test]
+    fn can_record_to_apng() {
+        let bytes = recording().to_apng(2, Duration::from_millis(100)).unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let reader = decoder.read_info().unwrap();
+        let animation = reader.info().animation_control().unwrap();
+
+        assert_eq!(animation.num_frames, 3);
+        assert_eq!((reader.info().width, reader.info().height), (6, 4));
+    }
+
+    #[test]
+    fn refuses_animations_too_big_to_encode() {
+        let mut recorder = Recorder::new();
+        recorder.record(|| Scene::from_lines(&[&".".repeat(9000)]));
+
+        let wide = recorder.to_gif(8, Duration::from_millis(100));
+        let slow = recording().to_apng(2, Duration::from_secs(70));
+
+        assert_eq!(
+            wide.unwrap_err().get_reason(),
+            "width of 72000 is more than an animation can hold (65535)"
+        );
+        assert_eq!(
+            slow.unwrap_err().get_reason(),
+            "frame delay in ms of 70000 is more than an animation can hold (65535)"
+        );
+    }
+}
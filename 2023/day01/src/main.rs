@@ -1,144 +1,370 @@
 use crate::calibration::*;
+use std::{env, fs::File, io::BufReader};
 
 fn main() {
-    let result = calculate_sum_of_calibration_document(&String::from("./calibration.txt"));
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("report") {
+        return print_report(args.get(2).map_or("./calibration.txt", |f| f.as_str()));
+    }
 
-    match result {
-        Ok(value) => {
-            println!("Sum of calibrations: {}", value)
-        }
-        Err(err) => {
-            println!("Failed {}", err.get_reason())
+    let mode = match args.iter().any(|a| a == "--skip-invalid") {
+        true => Mode::SkipInvalid,
+        false => Mode::Strict,
+    };
+
+    for (part, lexicon) in [(1, Lexicon::digits()), (2, Lexicon::english())] {
+        let calibrator = Calibrator::new(lexicon).unwrap();
+        let result = calculate_sum_of_calibration_document(
+            &String::from("./calibration.txt"),
+            &calibrator,
+            mode,
+        );
+
+        match result {
+            Ok(value) => {
+                println!("Part {} sum of calibrations: {}", part, value)
+            }
+            Err(err) => {
+                println!("Failed {}", err.get_reason())
+            }
         }
     }
 }
 
+/// Print what was found on every line, then the sum of the lines that have digits.
+fn print_report(filename: &str) {
+    let calibrator = Calibrator::new(Lexicon::english()).unwrap();
+    let file = BufReader::new(File::open(filename).unwrap());
+
+    let mut sum = 0;
+    for report in reports(file, &calibrator) {
+        println!("{}", report);
+        sum += report.value().unwrap_or(0);
+    }
+
+    println!("Sum of valid calibrations: {}", sum);
+}
+
 mod calibration {
+    use aho_corasick::AhoCorasick;
     use common::core::Error;
     use common::core::Result;
-    use std::fs::read_to_string;
+    use std::cmp::Reverse;
+    use std::fmt;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
 
-    pub fn calculate_sum_of_calibration_document(filename: &str) -> Result<i32> {
-        let lines = match read_lines(&filename) {
-            Ok(s) => s,
-            Err(e) => return Err(e),
-        };
+    const NUMBERS: [&str; 9] = [
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    ];
 
-        let line_values: Vec<i32> = match lines.iter().map(|l| sum_line(&l)).collect() {
-            Ok(s) => s,
-            Err(e) => return Err(e),
-        };
+    /// The tokens that spell a digit. Tokens can be any non-empty UTF-8 text and may overlap.
+    #[derive(Clone, Debug)]
+    pub struct Lexicon {
+        tokens: Vec<(String, i32)>,
+    }
 
-        let total_sum = line_values.iter().sum();
+    impl Lexicon {
+        pub fn new<'a>(tokens: impl IntoIterator<Item = (&'a str, i32)>) -> Self {
+            Lexicon {
+                tokens: tokens
+                    .into_iter()
+                    .map(|(token, digit)| (String::from(token), digit))
+                    .collect(),
+            }
+        }
 
-        return Ok(total_sum);
-    }
+        /// Just the digits `0` to `9`.
+        pub fn digits() -> Self {
+            Lexicon {
+                tokens: (0..10).map(|d| (d.to_string(), d)).collect(),
+            }
+        }
 
-    fn sum_line(line: &str) -> Result<i32> {
-        let first_digit = match find_first_digit(line) {
-            Some(v) => v,
-            None => return Err(Error::new("can't find first digit")),
-        };
+        /// Digits plus the English words `one` to `nine`.
+        pub fn english() -> Self {
+            Self::digits().with_words(&NUMBERS)
+        }
 
-        let second_digit = match find_last_digit(line) {
-            Some(v) => v,
-            None => return Err(Error::new("can't find second digit")),
-        };
+        /// Add words for the digits one upwards, e.g. `["eins", "zwei", ...]`.
+        pub fn with_words(self, words: &[&str]) -> Self {
+            self.with(words.iter().enumerate().map(|(i, &w)| (w, i as i32 + 1)))
+        }
 
-        return Ok((first_digit * 10) + second_digit);
+        pub fn with<'a>(mut self, tokens: impl IntoIterator<Item = (&'a str, i32)>) -> Self {
+            self.tokens.extend(Lexicon::new(tokens).tokens);
+            self
+        }
     }
 
-    fn read_lines(filename: &str) -> Result<Vec<String>> {
-        return match read_to_string(filename) {
-            Ok(s) => return Ok(s.lines().map(String::from).collect()),
-            Err(_) => Err(Error::new("can't read file")),
-        };
+    /// A digit found in a line, with the token that spelled it and its byte offset.
+    #[derive(PartialEq, Eq, Clone, Debug)]
+    pub struct Digit {
+        pub value: i32,
+        pub token: String,
+        pub start: usize,
     }
 
-    const NUMBERS: [&str; 9] = [
-        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
-    ];
+    #[derive(PartialEq, Eq, Clone, Debug)]
+    pub struct Calibration {
+        pub first: Digit,
+        pub last: Digit,
+    }
+
+    impl Calibration {
+        pub fn value(&self) -> i32 {
+            (self.first.value * 10) + self.last.value
+        }
+    }
 
-    fn find_first_digit(line: &str) -> Option<i32> {
-        for i in 0..line.chars().count() {
-            let remaining_text = &line[i..];
+    /// Finds the first and last digit of a line in a single pass over its bytes.
+    pub struct Calibrator {
+        lexicon: Lexicon,
+        automaton: AhoCorasick,
+    }
 
-            let leading_char = remaining_text.chars().nth(0).unwrap();
-            if leading_char.is_digit(10) {
-                let digit = leading_char.to_digit(10).unwrap() as i32;
-                return Some(digit);
+    impl Calibrator {
+        pub fn new(lexicon: Lexicon) -> Result<Self> {
+            if lexicon.tokens.iter().any(|(token, _)| token.is_empty()) {
+                return Err(Error::new("lexicon has an empty token"));
             }
 
-            for (j, number) in NUMBERS.iter().enumerate() {
-                let number_length = number.chars().count();
-                let remaining_length = remaining_text.chars().count();
-                if number_length > remaining_length {
-                    continue;
-                }
+            let automaton = AhoCorasick::new(lexicon.tokens.iter().map(|(token, _)| token))
+                .map_err(|e| Error::new(&format!("can't build lexicon: {}", e)))?;
+
+            Ok(Calibrator { lexicon, automaton })
+        }
+
+        /// Matches may overlap, so `twone` starts with 2 and ends with 1. When tokens start at
+        /// the same place the longest wins.
+        pub fn calibrate(&self, line: &str) -> Option<Calibration> {
+            let mut first: Option<aho_corasick::Match> = None;
+            let mut last: Option<aho_corasick::Match> = None;
 
-                let text_to_compare = remaining_text[..number_length].to_string();
+            for m in self.automaton.find_overlapping_iter(line) {
+                let earlier = |f: &aho_corasick::Match| {
+                    (m.start(), Reverse(m.len())) < (f.start(), Reverse(f.len()))
+                };
+                if first.as_ref().is_none_or(earlier) {
+                    first = Some(m);
+                }
 
-                if number.eq(&text_to_compare) {
-                    return Some((j + 1) as i32);
+                let later = |l: &aho_corasick::Match| (m.start(), m.len()) > (l.start(), l.len());
+                if last.as_ref().is_none_or(later) {
+                    last = Some(m);
                 }
             }
+
+            Some(Calibration {
+                first: self.digit(first?),
+                last: self.digit(last?),
+            })
         }
 
-        return None;
+        fn digit(&self, m: aho_corasick::Match) -> Digit {
+            let (token, value) = &self.lexicon.tokens[m.pattern().as_usize()];
+
+            Digit {
+                value: *value,
+                token: token.clone(),
+                start: m.start(),
+            }
+        }
     }
 
-    // todo repetative
-    fn find_last_digit(line: &str) -> Option<i32> {
-        for i in 0..line.chars().count() {
-            let remaining_text = &line[..line.chars().count() - i];
+    /// What to do with a line that has no digits.
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub enum Mode {
+        /// Fail the whole document.
+        Strict,
+        /// Leave the line out of the sum.
+        SkipInvalid,
+    }
 
-            let trailing_char = remaining_text.chars().last().unwrap();
-            if trailing_char.is_digit(10) {
-                let digit = trailing_char.to_digit(10).unwrap() as i32;
-                return Some(digit);
-            }
+    /// What was found on one line, numbered from one.
+    pub struct LineReport {
+        pub line: usize,
+        pub calibration: Result<Calibration>,
+    }
 
-            for (j, number) in NUMBERS.iter().enumerate() {
-                let number_length = number.chars().count();
-                let remaining_length = remaining_text.chars().count();
-                if number_length > remaining_length {
-                    continue;
-                }
+    impl LineReport {
+        pub fn value(&self) -> Option<i32> {
+            self.calibration.as_ref().ok().map(|c| c.value())
+        }
+    }
+
+    impl fmt::Display for LineReport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.calibration {
+                Ok(c) => write!(
+                    f,
+                    "line {}: first {} ('{}'), last {} ('{}'), value {}",
+                    self.line,
+                    c.first.value,
+                    c.first.token,
+                    c.last.value,
+                    c.last.token,
+                    c.value()
+                ),
+                Err(e) => write!(f, "line {}: {}", self.line, e.get_reason()),
+            }
+        }
+    }
 
-                let text_to_compare =
-                    remaining_text[remaining_text.chars().count() - number_length..].to_string();
+    /// Calibrate a document one line at a time as it is read.
+    pub fn reports<'a>(
+        reader: impl BufRead + 'a,
+        calibrator: &'a Calibrator,
+    ) -> impl Iterator<Item = LineReport> + 'a {
+        reader.lines().enumerate().map(|(i, line)| LineReport {
+            line: i + 1,
+            calibration: match line {
+                Ok(line) => calibrator
+                    .calibrate(&line)
+                    .ok_or_else(|| Error::new("can't find a digit")),
+                Err(e) => Err(Error::new(&format!("can't read line: {}", e))),
+            },
+        })
+    }
 
-                if number.eq(&text_to_compare) {
-                    return Some((j + 1) as i32);
+    pub fn sum_calibrations(
+        reader: impl BufRead,
+        calibrator: &Calibrator,
+        mode: Mode,
+    ) -> Result<i32> {
+        let mut sum = 0;
+        for report in reports(reader, calibrator) {
+            match (report.calibration, mode) {
+                (Ok(calibration), _) => sum += calibration.value(),
+                (Err(_), Mode::SkipInvalid) => (),
+                (Err(e), Mode::Strict) => {
+                    return Err(Error::new(&format!(
+                        "line {}: {}",
+                        report.line,
+                        e.get_reason()
+                    )))
                 }
             }
         }
 
-        return None;
+        Ok(sum)
+    }
+
+    pub fn calculate_sum_of_calibration_document(
+        filename: &str,
+        calibrator: &Calibrator,
+        mode: Mode,
+    ) -> Result<i32> {
+        let file = File::open(filename).map_err(|_| Error::new("can't read file"))?;
+
+        sum_calibrations(BufReader::new(file), calibrator, mode)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::calibration;
+    use crate::calibration::{self, reports, sum_calibrations, Calibrator, Lexicon, Mode};
 
     #[test]
     fn can_calculate_sum_of_part_one_example_document() {
         let example_file = String::from("./example_calibration_part_one.txt");
+        let calibrator = Calibrator::new(Lexicon::digits()).unwrap();
 
-        let result = calibration::calculate_sum_of_calibration_document(&example_file);
+        let result = calibration::calculate_sum_of_calibration_document(
+            &example_file,
+            &calibrator,
+            Mode::Strict,
+        );
 
-        assert_eq!(result.is_err(), false);
+        assert!(result.is_ok());
         assert_eq!(result.unwrap(), 142);
     }
 
     #[test]
     fn can_calculate_sum_of_part_two_example_document() {
         let example_file = String::from("./example_calibration_part_two.txt");
+        let calibrator = Calibrator::new(Lexicon::english()).unwrap();
 
-        let result = calibration::calculate_sum_of_calibration_document(&example_file);
+        let result = calibration::calculate_sum_of_calibration_document(
+            &example_file,
+            &calibrator,
+            Mode::Strict,
+        );
 
-        assert_eq!(result.is_err(), false);
+        assert!(result.is_ok());
         assert_eq!(result.unwrap(), 281);
     }
+
+    #[test]
+    fn can_find_overlapping_words() {
+        let calibrator = Calibrator::new(Lexicon::english()).unwrap();
+
+        let calibration = calibrator.calibrate("xtwone3eightwo").unwrap();
+
+        assert_eq!(calibration.value(), 22);
+        assert_eq!(calibration.first.token, "two");
+        assert_eq!(calibration.first.start, 1);
+        assert_eq!(calibration.last.token, "two");
+        assert_eq!(calibration.last.start, 11);
+    }
+
+    #[test]
+    fn can_use_other_languages_in_utf8() {
+        let german = Lexicon::digits().with_words(&[
+            "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+        ]);
+        let calibrator = Calibrator::new(german).unwrap();
+
+        let calibration = calibrator.calibrate("ÄÖfünfßzweiüneun€").unwrap();
+
+        assert_eq!(calibration.value(), 59);
+        assert_eq!(calibration.first.token, "fünf");
+        assert_eq!(calibration.last.token, "neun");
+        assert_eq!(calibrator.calibrate("ÄÖÜ"), None);
+    }
+
+    #[test]
+    fn can_prefer_longest_token_starting_at_same_place() {
+        let lexicon = Lexicon::new([("se", 1), ("seven", 7), ("en", 5)]);
+        let calibrator = Calibrator::new(lexicon).unwrap();
+
+        let calibration = calibrator.calibrate("seven").unwrap();
+
+        assert_eq!(calibration.first.token, "seven");
+        assert_eq!(calibration.last.token, "en");
+    }
+
+    #[test]
+    fn can_report_every_line() {
+        let calibrator = Calibrator::new(Lexicon::english()).unwrap();
+        let document = "two1nine\nabc\nzoneight234\n";
+
+        let lines: Vec<String> = reports(document.as_bytes(), &calibrator)
+            .map(|r| r.to_string())
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "line 1: first 2 ('two'), last 9 ('nine'), value 29",
+                "line 2: can't find a digit",
+                "line 3: first 1 ('one'), last 4 ('4'), value 14",
+            ]
+        );
+    }
+
+    #[test]
+    fn can_fail_or_skip_lines_without_digits() {
+        let calibrator = Calibrator::new(Lexicon::digits()).unwrap();
+        let document = "a1b2\nnothing\n7\n";
+
+        let strict = sum_calibrations(document.as_bytes(), &calibrator, Mode::Strict);
+        let skipped = sum_calibrations(document.as_bytes(), &calibrator, Mode::SkipInvalid);
+
+        assert_eq!(
+            strict.unwrap_err().get_reason(),
+            "line 2: can't find a digit"
+        );
+        assert_eq!(skipped.unwrap(), 12 + 77);
+    }
 }
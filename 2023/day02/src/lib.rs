@@ -0,0 +1,152 @@
+pub mod block_game {
+    use common::core::{Error, Result};
+    use std::collections::BTreeMap;
+
+    /// The colours in the puzzle's bag.
+    pub const COLOURS: [&str; 3] = ["red", "green", "blue"];
+
+    /// Cube counts by colour.
+    pub type Cubes = BTreeMap<String, i32>;
+
+    pub fn sum_of_ids_of_possible_games(bag: &Bag, games: &[Game]) -> i32 {
+        games
+            .iter()
+            .filter(|g| bag.is_game_possible(g))
+            .map(|g| g.id)
+            .sum()
+    }
+
+    pub fn sum_of_minimal_bag_powers(games: &[Game]) -> i32 {
+        games
+            .iter()
+            .map(Bag::create_minimal_bag)
+            .map(|b| b.get_power())
+            .sum()
+    }
+
+    pub struct Game {
+        pub id: i32,
+        pub rounds: Vec<Round>,
+    }
+
+    pub struct Round {
+        pub cubes: Cubes,
+    }
+
+    impl Round {
+        pub fn new<'a>(cubes: impl IntoIterator<Item = (&'a str, i32)>) -> Self {
+            Round {
+                cubes: to_cubes(cubes),
+            }
+        }
+
+        pub fn count(&self, colour: &str) -> i32 {
+            self.cubes.get(colour).copied().unwrap_or(0)
+        }
+    }
+
+    pub struct Bag {
+        pub cubes: Cubes,
+    }
+
+    impl Bag {
+        pub fn new<'a>(cubes: impl IntoIterator<Item = (&'a str, i32)>) -> Self {
+            Bag {
+                cubes: to_cubes(cubes),
+            }
+        }
+
+        pub fn count(&self, colour: &str) -> i32 {
+            self.cubes.get(colour).copied().unwrap_or(0)
+        }
+
+        pub fn is_round_possibe(&self, round: &Round) -> bool {
+            round
+                .cubes
+                .iter()
+                .all(|(colour, &number)| self.count(colour) >= number)
+        }
+
+        pub fn is_game_possible(&self, game: &Game) -> bool {
+            game.rounds.iter().all(|r| self.is_round_possibe(r))
+        }
+
+        /// The fewest cubes of every colour seen in the game that make it possible.
+        pub fn create_minimal_bag(game: &Game) -> Self {
+            let mut cubes = Cubes::new();
+            for round in game.rounds.iter() {
+                for (colour, &number) in round.cubes.iter() {
+                    let count = cubes.entry(colour.clone()).or_insert(0);
+                    *count = (*count).max(number);
+                }
+            }
+
+            Bag { cubes }
+        }
+
+        pub fn get_power(&self) -> i32 {
+            self.cubes.values().product()
+        }
+    }
+
+    fn to_cubes<'a>(cubes: impl IntoIterator<Item = (&'a str, i32)>) -> Cubes {
+        cubes
+            .into_iter()
+            .map(|(colour, number)| (String::from(colour), number))
+            .collect()
+    }
+
+    /// Parse one game per line. Every round counts each of `colours`, so a colour missing from a
+    /// round has zero cubes; a colour not in `colours` is an error.
+    pub fn deserialize_games(lines: &[String], colours: &[&str]) -> Result<Vec<Game>> {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                deserialize_game_line(l, colours)
+                    .map_err(|e| Error::new(&format!("line {}: {}", i + 1, e.get_reason())))
+            })
+            .collect()
+    }
+
+    pub fn deserialize_game_line(line: &str, colours: &[&str]) -> Result<Game> {
+        let (id_part, rounds_part) = line
+            .split_once(":")
+            .ok_or_else(|| Error::new("missing ':' after the game id"))?;
+
+        let id = id_part
+            .strip_prefix("Game ")
+            .and_then(|id| id.trim().parse::<i32>().ok())
+            .ok_or_else(|| Error::new(&format!("bad game id '{}'", id_part)))?;
+
+        let rounds = rounds_part
+            .split(";")
+            .map(|p| deserialize_round(p, colours))
+            .collect::<Result<Vec<Round>>>()?;
+
+        Ok(Game { id, rounds })
+    }
+
+    fn deserialize_round(text: &str, colours: &[&str]) -> Result<Round> {
+        let mut round = Round::new(colours.iter().map(|&c| (c, 0)));
+
+        for part in text.split(",") {
+            let (number, colour) = part
+                .trim()
+                .split_once(" ")
+                .ok_or_else(|| Error::new(&format!("bad cube count '{}'", part.trim())))?;
+
+            let number = number
+                .parse::<i32>()
+                .map_err(|_| Error::new(&format!("bad number of cubes '{}'", number)))?;
+
+            let count = round
+                .cubes
+                .get_mut(colour.trim())
+                .ok_or_else(|| Error::new(&format!("unknown colour '{}'", colour.trim())))?;
+            *count += number;
+        }
+
+        Ok(round)
+    }
+}
@@ -0,0 +1,6 @@
+pub mod bytecode;
+pub mod checker;
+pub mod debugger;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
@@ -0,0 +1,659 @@
+use std::collections::HashMap;
+
+use common::core::{Error, Result};
+
+use super::interpreter::{apply, MAIN};
+use super::lexer::Operand;
+use super::parser::{Expression, Method, Operator, Printable, Program, Statement, StatementKind};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Instruction {
+    Push(i32),
+    /// Declare a variable with a number, failing if it's already declared.
+    DeclareNumber(usize, i32),
+    /// Declare a variable with the value of another, failing if it's already declared or the
+    /// other isn't.
+    DeclareCopy(usize, usize),
+    Load(usize),
+    Store(usize),
+    Binary(Operator),
+    Jump(usize),
+    JumpIfZero(usize),
+    Call(usize),
+    /// Store the value returned by the last call, failing if it didn't return one.
+    StoreResult(usize),
+    /// Throw away the value returned by the last call, if any.
+    DiscardResult,
+    ReturnValue,
+    Return,
+    /// The end of a method that should have returned a value.
+    MissingReturn,
+    Read,
+    PrintText(usize),
+    PrintValue,
+    /// Stop with the error message in the string table. Calls to missing methods and with the
+    /// wrong number of arguments compile to this, so they only fail if they're reached.
+    Fail(usize),
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub entry: usize,
+    pub parameters: usize,
+    /// The name of the variable in each slot.
+    pub locals: Vec<String>,
+}
+
+/// A program compiled for the stack machine. Variables are resolved to slots in their method's
+/// frame, and every slot starts undeclared, so declarations are checked as they run just like
+/// in the interpreter.
+#[derive(Debug)]
+pub struct Bytecode {
+    pub code: Vec<Instruction>,
+    pub lines: Vec<usize>,
+    pub strings: Vec<String>,
+    pub functions: Vec<Function>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Run {
+    pub output: Vec<String>,
+    pub instructions: u64,
+}
+
+pub fn compile(program: &Program) -> Result<Bytecode> {
+    let mut methods: Vec<&Method> = program.methods.values().collect();
+    methods.sort_by_key(|m| m.line);
+
+    let mut compiler = Compiler {
+        bytecode: Bytecode {
+            code: vec![],
+            lines: vec![],
+            strings: vec![],
+            functions: vec![],
+        },
+        function_ids: methods
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (m.name.as_str(), i + 1))
+            .collect(),
+        methods: program
+            .methods
+            .iter()
+            .map(|(k, v)| (k.as_str(), v))
+            .collect(),
+        slots: HashMap::new(),
+        returns_value: false,
+    };
+
+    compiler.compile_function(MAIN, &[], false, &program.main)?;
+    for method in methods {
+        compiler.compile_function(
+            &method.name,
+            &method.parameters,
+            method.returns_value,
+            &method.body,
+        )?;
+    }
+
+    Ok(compiler.bytecode)
+}
+
+struct Compiler<'a> {
+    bytecode: Bytecode,
+    function_ids: HashMap<&'a str, usize>,
+    methods: HashMap<&'a str, &'a Method>,
+    slots: HashMap<String, usize>,
+    /// Whether the function being compiled must return a value.
+    returns_value: bool,
+}
+
+impl<'a> Compiler<'a> {
+    fn compile_function(
+        &mut self,
+        name: &str,
+        parameters: &[String],
+        returns_value: bool,
+        body: &[Statement],
+    ) -> Result<()> {
+        self.slots = parameters
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.clone(), i))
+            .collect();
+        self.returns_value = returns_value;
+        let entry = self.bytecode.code.len();
+
+        self.compile_block(body)?;
+
+        let end_line = body.last().map(|s| s.line).unwrap_or(0);
+        let mut locals: Vec<(&String, &usize)> = self.slots.iter().collect();
+        locals.sort_by_key(|(_, slot)| **slot);
+        let locals = locals.into_iter().map(|(name, _)| name.clone()).collect();
+
+        self.emit(
+            if returns_value {
+                Instruction::MissingReturn
+            } else {
+                Instruction::Return
+            },
+            end_line,
+        );
+
+        self.bytecode.functions.push(Function {
+            name: String::from(name),
+            entry,
+            parameters: parameters.len(),
+            locals,
+        });
+
+        Ok(())
+    }
+
+    fn compile_block(&mut self, statements: &[Statement]) -> Result<()> {
+        for statement in statements {
+            self.compile_statement(statement)?;
+        }
+
+        Ok(())
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) -> Result<()> {
+        let line = statement.line;
+        match &statement.kind {
+            StatementKind::Print(Printable::Text(s)) => {
+                self.bytecode.strings.push(s.clone());
+                self.emit(
+                    Instruction::PrintText(self.bytecode.strings.len() - 1),
+                    line,
+                );
+            }
+            StatementKind::Print(Printable::Operand(o)) => {
+                self.compile_operand(o, line)?;
+                self.emit(Instruction::PrintValue, line);
+            }
+            StatementKind::Declare { name, value } => {
+                let slot = self.slot(name);
+                let instruction = match value {
+                    Operand::Number(n) => Instruction::DeclareNumber(slot, *n),
+                    Operand::Variable(from) => Instruction::DeclareCopy(slot, self.slot(from)),
+                };
+                self.emit(instruction, line);
+            }
+            StatementKind::Assign { name, expression } => {
+                self.compile_expression(expression, line)?;
+                let slot = self.slot(name);
+                self.emit(Instruction::Store(slot), line);
+            }
+            StatementKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.compile_operand(condition, line)?;
+                let to_otherwise = self.emit(Instruction::JumpIfZero(0), line);
+                self.compile_block(then)?;
+                let to_end = self.emit(Instruction::Jump(0), line);
+                self.patch(to_otherwise);
+                self.compile_block(otherwise)?;
+                self.patch(to_end);
+            }
+            StatementKind::While { condition, body } => {
+                let start = self.bytecode.code.len();
+                self.compile_operand(condition, line)?;
+                let to_end = self.emit(Instruction::JumpIfZero(0), line);
+                self.compile_block(body)?;
+                self.emit(Instruction::Jump(start), line);
+                self.patch(to_end);
+            }
+            StatementKind::Call {
+                target,
+                method,
+                arguments,
+            } => {
+                for argument in arguments {
+                    self.compile_operand(argument, line)?;
+                }
+
+                let problem = match self.function_ids.get(method.as_str()) {
+                    None => Some(format!("no method called '{}'", method)),
+                    Some(_)
+                        if self.methods[method.as_str()].parameters.len() != arguments.len() =>
+                    {
+                        Some(format!(
+                            "'{}' takes {} arguments but was given {}",
+                            method,
+                            self.methods[method.as_str()].parameters.len(),
+                            arguments.len()
+                        ))
+                    }
+                    Some(_) => None,
+                };
+                if let Some(problem) = problem {
+                    self.bytecode
+                        .strings
+                        .push(error(line, &problem).get_reason().clone());
+                    self.emit(Instruction::Fail(self.bytecode.strings.len() - 1), line);
+                    return Ok(());
+                }
+                self.emit(Instruction::Call(self.function_ids[method.as_str()]), line);
+
+                match target {
+                    Some(target) => {
+                        let slot = self.slot(target);
+                        self.emit(Instruction::StoreResult(slot), line);
+                    }
+                    None => {
+                        self.emit(Instruction::DiscardResult, line);
+                    }
+                }
+            }
+            StatementKind::ReadInteger { target } => {
+                self.emit(Instruction::Read, line);
+                let slot = self.slot(target);
+                self.emit(Instruction::Store(slot), line);
+            }
+            StatementKind::Return(Some(o)) => {
+                self.compile_operand(o, line)?;
+                self.emit(Instruction::ReturnValue, line);
+            }
+            // A bare return from a method that gives a value fails the same as falling off the end
+            StatementKind::Return(None) if self.returns_value => {
+                self.emit(Instruction::MissingReturn, line);
+            }
+            StatementKind::Return(None) => {
+                self.emit(Instruction::Return, line);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compile_expression(&mut self, expression: &Expression, line: usize) -> Result<()> {
+        self.compile_operand(&expression.first, line)?;
+        for (operator, operand) in expression.operations.iter() {
+            self.compile_operand(operand, line)?;
+            self.emit(Instruction::Binary(*operator), line);
+        }
+
+        Ok(())
+    }
+
+    fn compile_operand(&mut self, operand: &Operand, line: usize) -> Result<()> {
+        let instruction = match operand {
+            Operand::Number(n) => Instruction::Push(*n),
+            Operand::Variable(name) => Instruction::Load(self.slot(name)),
+        };
+        self.emit(instruction, line);
+
+        Ok(())
+    }
+
+    /// The slot for a variable, given out the first time the name is seen in the method.
+    fn slot(&mut self, name: &str) -> usize {
+        let next = self.slots.len();
+        *self.slots.entry(String::from(name)).or_insert(next)
+    }
+
+    fn emit(&mut self, instruction: Instruction, line: usize) -> usize {
+        self.bytecode.code.push(instruction);
+        self.bytecode.lines.push(line);
+        self.bytecode.code.len() - 1
+    }
+
+    /// Point the jump at `address` to the next instruction.
+    fn patch(&mut self, address: usize) {
+        let target = self.bytecode.code.len();
+        self.bytecode.code[address] = match self.bytecode.code[address] {
+            Instruction::Jump(_) => Instruction::Jump(target),
+            Instruction::JumpIfZero(_) => Instruction::JumpIfZero(target),
+            other => panic!("can't patch {:?}", other),
+        };
+    }
+}
+
+struct Frame {
+    return_address: usize,
+    base: usize,
+    function: usize,
+}
+
+/// Run compiled code on a stack machine, counting the instructions executed.
+pub fn execute<I>(bytecode: &Bytecode, input: I) -> Result<Run>
+where
+    I: IntoIterator<Item = i32>,
+{
+    let mut input = input.into_iter();
+    let mut output: Vec<String> = vec![];
+    let mut instructions: u64 = 0;
+
+    let mut stack: Vec<i32> = vec![];
+    let mut locals: Vec<i32> = vec![0; bytecode.functions[0].locals.len()];
+    let mut declared: Vec<bool> = vec![false; locals.len()];
+    let mut function = 0;
+    let mut frames: Vec<Frame> = vec![];
+    let mut base = 0;
+    let mut result: Option<i32> = None;
+    let mut pc = bytecode.functions[0].entry;
+
+    loop {
+        let instruction = bytecode.code[pc];
+        let line = bytecode.lines[pc];
+        instructions += 1;
+        pc += 1;
+
+        let undeclared = |slot: usize| {
+            let name = &bytecode.functions[function].locals[slot];
+            error(line, &format!("'{}' isn't declared", name))
+        };
+
+        match instruction {
+            Instruction::Push(n) => stack.push(n),
+            Instruction::DeclareNumber(slot, _) | Instruction::DeclareCopy(slot, _) => {
+                if declared[base + slot] {
+                    let name = &bytecode.functions[function].locals[slot];
+                    return Err(error(line, &format!("'{}' is already declared", name)));
+                }
+                locals[base + slot] = match instruction {
+                    Instruction::DeclareCopy(_, from) if !declared[base + from] => {
+                        return Err(undeclared(from))
+                    }
+                    Instruction::DeclareCopy(_, from) => locals[base + from],
+                    Instruction::DeclareNumber(_, n) => n,
+                    _ => unreachable!(),
+                };
+                declared[base + slot] = true;
+            }
+            Instruction::Load(slot) => match declared[base + slot] {
+                true => stack.push(locals[base + slot]),
+                false => return Err(undeclared(slot)),
+            },
+            Instruction::Store(slot) => match declared[base + slot] {
+                true => locals[base + slot] = stack.pop().unwrap(),
+                false => return Err(undeclared(slot)),
+            },
+            Instruction::Binary(operator) => {
+                let right = stack.pop().unwrap();
+                let left = stack.pop().unwrap();
+                let value =
+                    apply(operator, left, right).ok_or_else(|| error(line, "division by zero"))?;
+                stack.push(value);
+            }
+            Instruction::Jump(address) => pc = address,
+            Instruction::JumpIfZero(address) => {
+                if stack.pop().unwrap() == 0 {
+                    pc = address;
+                }
+            }
+            Instruction::Call(id) => {
+                frames.push(Frame {
+                    return_address: pc,
+                    base,
+                    function,
+                });
+                function = id;
+                let callee = &bytecode.functions[id];
+
+                base = locals.len();
+                locals.resize(base + callee.locals.len(), 0);
+                declared.resize(base + callee.locals.len(), false);
+                let arguments = stack.len() - callee.parameters;
+                locals[base..base + callee.parameters].copy_from_slice(&stack[arguments..]);
+                declared[base..base + callee.parameters].fill(true);
+                stack.truncate(arguments);
+
+                pc = callee.entry;
+            }
+            Instruction::StoreResult(slot) => {
+                let value = result.take().ok_or_else(|| {
+                    error(
+                        line,
+                        &format!("'{}' doesn't return a value", called(bytecode, pc - 1)),
+                    )
+                })?;
+                if !declared[base + slot] {
+                    return Err(undeclared(slot));
+                }
+                locals[base + slot] = value;
+            }
+            Instruction::DiscardResult => result = None,
+            Instruction::ReturnValue | Instruction::Return => {
+                result = match instruction {
+                    Instruction::ReturnValue => stack.pop(),
+                    _ => None,
+                };
+
+                let Some(frame) = frames.pop() else {
+                    break;
+                };
+                locals.truncate(base);
+                declared.truncate(base);
+                base = frame.base;
+                function = frame.function;
+                pc = frame.return_address;
+            }
+            Instruction::MissingReturn => {
+                let frame = frames.last().unwrap();
+                return Err(error(
+                    bytecode.lines[frame.return_address - 1],
+                    &format!(
+                        "'{}' finished without returning a value",
+                        called(bytecode, frame.return_address)
+                    ),
+                ));
+            }
+            Instruction::Read => {
+                let value = input
+                    .next()
+                    .ok_or_else(|| error(line, "ran out of input"))?;
+                stack.push(value);
+            }
+            Instruction::PrintText(index) => output.push(bytecode.strings[index].clone()),
+            Instruction::PrintValue => output.push(stack.pop().unwrap().to_string()),
+            Instruction::Fail(index) => return Err(Error::new(&bytecode.strings[index])),
+        }
+    }
+
+    Ok(Run {
+        output,
+        instructions,
+    })
+}
+
+/// Name of the function called by the instruction before `return_address`.
+fn called(bytecode: &Bytecode, return_address: usize) -> &str {
+    match bytecode.code[return_address - 1] {
+        Instruction::Call(id) => &bytecode.functions[id].name,
+        _ => "?",
+    }
+}
+
+fn error(line: usize, message: &str) -> Error {
+    Error::new(&format!("line {}: {}", line, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compile, execute};
+    use crate::arnoldc::interpreter::run;
+    use crate::arnoldc::parser::parse;
+    use crate::harness::{generate_games, to_input};
+    use common::random::Random;
+    use std::fs::read_to_string;
+
+    /// Input for between 1 and 20 random games, repeatable from the generator's seed.
+    fn random_games(random: &mut Random) -> Vec<i32> {
+        let count = 1 + random.next(20) as usize;
+
+        to_input(&generate_games(random, 1, count))
+    }
+
+    fn assert_same_as_interpreter(source: &str, input: &[i32]) {
+        let program = parse(source).unwrap();
+        let bytecode = compile(&program).unwrap();
+
+        let expected = run(&program, input.iter().copied()).map_err(|e| e.get_reason().clone());
+        let actual = execute(&bytecode, input.iter().copied())
+            .map(|r| r.output)
+            .map_err(|e| e.get_reason().clone());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn gives_same_answers_as_interpreter_for_random_games() {
+        let mut random = Random::new(2023);
+        for filename in ["./main.arnoldc", "./part_2.arnoldc"] {
+            let source = read_to_string(filename).unwrap();
+            for _ in 0..200 {
+                assert_same_as_interpreter(&source, &random_games(&mut random));
+            }
+        }
+    }
+
+    #[test]
+    fn gives_same_errors_as_interpreter() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE n
+    YOU SET US UP 0
+    GET YOUR ASS TO MARS n
+    DO IT NOW
+    I WANT TO ASK YOU A BUNCH OF QUESTIONS AND I WANT TO HAVE THEM ANSWERED IMMEDIATELY
+    GET TO THE CHOPPER n
+    HERE IS MY INVITATION 10
+    HE HAD TO SPLIT n
+    ENOUGH TALK
+    TALK TO THE HAND n
+    GET YOUR ASS TO MARS n
+    DO IT NOW voidFUNC
+YOU HAVE BEEN TERMINATED
+
+LISTEN TO ME VERY CAREFULLY voidFUNC
+    TALK TO THE HAND \"void\"
+HASTA LA VISTA, BABY";
+
+        for input in [vec![], vec![0], vec![3]] {
+            assert_same_as_interpreter(source, &input);
+        }
+
+        let bare_return = |call: &str| {
+            format!(
+                "IT'S SHOWTIME
+    HEY CHRISTMAS TREE n
+    YOU SET US UP 0
+    {}
+    TALK TO THE HAND \"after\"
+YOU HAVE BEEN TERMINATED
+
+LISTEN TO ME VERY CAREFULLY valueFUNC
+GIVE THESE PEOPLE AIR
+    TALK TO THE HAND \"inside\"
+    I'LL BE BACK
+HASTA LA VISTA, BABY",
+                call
+            )
+        };
+        for call in [
+            "DO IT NOW valueFUNC",
+            "GET YOUR ASS TO MARS n\n    DO IT NOW valueFUNC",
+        ] {
+            assert_same_as_interpreter(&bare_return(call), &[]);
+        }
+    }
+
+    #[test]
+    fn counts_instructions() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE n
+    YOU SET US UP 3
+    STICK AROUND n
+        GET TO THE CHOPPER n
+        HERE IS MY INVITATION n
+        GET DOWN 1
+        ENOUGH TALK
+    CHILL
+YOU HAVE BEEN TERMINATED";
+        let bytecode = compile(&parse(source).unwrap()).unwrap();
+
+        let run = execute(&bytecode, []).unwrap();
+
+        // declare 1, each of 3 iterations 3 + 4, last check 2, return 1
+        assert_eq!(run.instructions, 1 + 3 * 7 + 2 + 1);
+    }
+
+    #[test]
+    fn agrees_with_interpreter_on_declarations() {
+        let wrap = |main: &str| {
+            format!(
+                "IT'S SHOWTIME\n{}\nYOU HAVE BEEN TERMINATED\n
+LISTEN TO ME VERY CAREFULLY twoFUNC
+    I NEED YOUR CLOTHES YOUR BOOTS AND YOUR MOTORCYCLE a
+    I NEED YOUR CLOTHES YOUR BOOTS AND YOUR MOTORCYCLE b
+GIVE THESE PEOPLE AIR
+    I'LL BE BACK a
+HASTA LA VISTA, BABY",
+                main
+            )
+        };
+
+        let programs = [
+            // declared only in a branch that isn't taken, then used
+            "HEY CHRISTMAS TREE flag
+    YOU SET US UP 0
+    BECAUSE I'M GOING TO SAY PLEASE flag
+        HEY CHRISTMAS TREE x
+        YOU SET US UP 5
+    YOU HAVE NO RESPECT FOR LOGIC
+    TALK TO THE HAND x",
+            // used before it's declared, then declared
+            "TALK TO THE HAND x
+    HEY CHRISTMAS TREE x
+    YOU SET US UP 5",
+            // used before it's declared, in a branch that isn't taken
+            "HEY CHRISTMAS TREE flag
+    YOU SET US UP 0
+    BECAUSE I'M GOING TO SAY PLEASE flag
+        TALK TO THE HAND x
+    YOU HAVE NO RESPECT FOR LOGIC
+    HEY CHRISTMAS TREE x
+    YOU SET US UP 5
+    TALK TO THE HAND x",
+            // declared again in a loop
+            "HEY CHRISTMAS TREE n
+    YOU SET US UP 2
+    STICK AROUND n
+        HEY CHRISTMAS TREE inside
+        YOU SET US UP n
+        GET TO THE CHOPPER n
+        HERE IS MY INVITATION n
+        GET DOWN 1
+        ENOUGH TALK
+    CHILL",
+            // declared from itself
+            "HEY CHRISTMAS TREE x
+    YOU SET US UP x",
+            // assigned and read into without being declared
+            "GET TO THE CHOPPER x
+    HERE IS MY INVITATION 1
+    ENOUGH TALK",
+            "GET YOUR ASS TO MARS x
+    DO IT NOW
+    I WANT TO ASK YOU A BUNCH OF QUESTIONS AND I WANT TO HAVE THEM ANSWERED IMMEDIATELY",
+            // calls that can't work, only reached some of the time
+            "TALK TO THE HAND \"before\"
+    DO IT NOW missingFUNC",
+            "HEY CHRISTMAS TREE flag
+    YOU SET US UP 0
+    BECAUSE I'M GOING TO SAY PLEASE flag
+        DO IT NOW twoFUNC 1
+    YOU HAVE NO RESPECT FOR LOGIC
+    GET YOUR ASS TO MARS result
+    DO IT NOW twoFUNC 1 2",
+        ];
+
+        for main in programs {
+            assert_same_as_interpreter(&wrap(main), &[7]);
+        }
+    }
+}
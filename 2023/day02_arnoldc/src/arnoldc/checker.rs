@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use common::core::Result;
+
+use super::lexer::{tokenize, Keyword, Operand, Token};
+use super::parser::{parse, Expression, Method, Printable, Program, Statement, StatementKind};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Problem {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Find mistakes the JVM toolchain would only show when compiling or running the program.
+/// Fails only if the source can't be tokenized.
+pub fn check(source: &str) -> Result<Vec<Problem>> {
+    let tokens = tokenize(source)?;
+
+    let problems = check_blocks(&tokens);
+    if !problems.is_empty() {
+        return Ok(problems);
+    }
+
+    let program = match parse(source) {
+        Ok(p) => p,
+        Err(e) => return Ok(vec![problem_from_reason(e.get_reason())]),
+    };
+
+    let mut checker = Checker {
+        program: &program,
+        problems: vec![],
+    };
+    checker.check_program();
+
+    let mut problems = checker.problems;
+    problems.sort_by_key(|p| p.line);
+    Ok(problems)
+}
+
+fn opened_by(keyword: Keyword) -> Option<Keyword> {
+    match keyword {
+        Keyword::End => Some(Keyword::Begin),
+        Keyword::EndMethod => Some(Keyword::DeclareMethod),
+        Keyword::EndIf => Some(Keyword::If),
+        Keyword::EndWhile => Some(Keyword::While),
+        _ => None,
+    }
+}
+
+fn describe(keyword: Keyword) -> &'static str {
+    match keyword {
+        Keyword::Begin => "IT'S SHOWTIME",
+        Keyword::End => "YOU HAVE BEEN TERMINATED",
+        Keyword::DeclareMethod => "LISTEN TO ME VERY CAREFULLY",
+        Keyword::EndMethod => "HASTA LA VISTA, BABY",
+        Keyword::If => "BECAUSE I'M GOING TO SAY PLEASE",
+        Keyword::Else => "BULLSHIT",
+        Keyword::EndIf => "YOU HAVE NO RESPECT FOR LOGIC",
+        Keyword::While => "STICK AROUND",
+        Keyword::EndWhile => "CHILL",
+        _ => "statement",
+    }
+}
+
+fn check_blocks(tokens: &[Token]) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut open: Vec<&Token> = vec![];
+
+    for token in tokens {
+        match token.keyword {
+            Keyword::Begin | Keyword::DeclareMethod | Keyword::If | Keyword::While => {
+                open.push(token)
+            }
+            Keyword::Else => {
+                if open.last().map(|t| t.keyword) != Some(Keyword::If) {
+                    problems.push(Problem {
+                        line: token.line,
+                        message: String::from("BULLSHIT outside BECAUSE I'M GOING TO SAY PLEASE"),
+                    });
+                }
+            }
+            keyword => {
+                let Some(opener) = opened_by(keyword) else {
+                    continue;
+                };
+
+                match open.iter().rposition(|t| t.keyword == opener) {
+                    Some(i) => {
+                        // Anything opened since the matching opener was never closed
+                        for unclosed in open.drain(i..).skip(1) {
+                            problems.push(unclosed_problem(unclosed));
+                        }
+                    }
+                    None => problems.push(Problem {
+                        line: token.line,
+                        message: format!("{} without {}", describe(keyword), describe(opener)),
+                    }),
+                }
+            }
+        }
+    }
+
+    for unclosed in open {
+        problems.push(unclosed_problem(unclosed));
+    }
+
+    problems.sort_by_key(|p| p.line);
+    problems
+}
+
+fn unclosed_problem(token: &Token) -> Problem {
+    let closer = [
+        Keyword::End,
+        Keyword::EndMethod,
+        Keyword::EndIf,
+        Keyword::EndWhile,
+    ]
+    .into_iter()
+    .find(|k| opened_by(*k) == Some(token.keyword))
+    .unwrap();
+
+    Problem {
+        line: token.line,
+        message: format!(
+            "{} is never closed by {}",
+            describe(token.keyword),
+            describe(closer)
+        ),
+    }
+}
+
+/// Parser errors are reported as "line N: reason".
+fn problem_from_reason(reason: &str) -> Problem {
+    let parsed = reason
+        .strip_prefix("line ")
+        .and_then(|r| r.split_once(": "))
+        .and_then(|(line, message)| Some((line.parse::<usize>().ok()?, message)));
+
+    match parsed {
+        Some((line, message)) => Problem {
+            line,
+            message: String::from(message),
+        },
+        None => Problem {
+            line: 0,
+            message: String::from(reason),
+        },
+    }
+}
+
+struct Checker<'a> {
+    program: &'a Program,
+    problems: Vec<Problem>,
+}
+
+impl<'a> Checker<'a> {
+    fn check_program(&mut self) {
+        let mut declared: HashMap<String, usize> = HashMap::new();
+        self.check_block(&self.program.main, &mut declared);
+
+        let mut methods: Vec<&Method> = self.program.methods.values().collect();
+        methods.sort_by_key(|m| m.line);
+        for method in methods {
+            let mut declared: HashMap<String, usize> = method
+                .parameters
+                .iter()
+                .map(|p| (p.clone(), method.line))
+                .collect();
+            self.check_block(&method.body, &mut declared);
+        }
+    }
+
+    fn check_block(&mut self, statements: &'a [Statement], declared: &mut HashMap<String, usize>) {
+        let mut returned_at: Option<usize> = None;
+        for statement in statements {
+            if let Some(return_line) = returned_at {
+                self.problem(
+                    statement.line,
+                    &format!("unreachable after I'LL BE BACK on line {}", return_line),
+                );
+                break;
+            }
+
+            self.check_statement(statement, declared);
+
+            if let StatementKind::Return(_) = statement.kind {
+                returned_at = Some(statement.line);
+            }
+        }
+    }
+
+    fn check_statement(&mut self, statement: &'a Statement, declared: &mut HashMap<String, usize>) {
+        let line = statement.line;
+        match &statement.kind {
+            StatementKind::Print(Printable::Operand(o)) => self.check_operand(o, declared, line),
+            StatementKind::Print(Printable::Text(_)) => (),
+            StatementKind::Declare { name, value } => {
+                self.check_operand(value, declared, line);
+                if let Some(first) = declared.get(name) {
+                    self.problem(
+                        line,
+                        &format!("'{}' is already declared on line {}", name, first),
+                    );
+                } else {
+                    declared.insert(name.clone(), line);
+                }
+            }
+            StatementKind::Assign { name, expression } => {
+                self.check_expression(expression, declared, line);
+                self.check_variable(name, declared, line);
+            }
+            StatementKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.check_operand(condition, declared, line);
+
+                // Only one branch runs, so afterwards only what both declared is certain
+                let mut then_declared = declared.clone();
+                self.check_block(then, &mut then_declared);
+                let mut otherwise_declared = declared.clone();
+                self.check_block(otherwise, &mut otherwise_declared);
+
+                *declared = then_declared
+                    .into_iter()
+                    .filter(|(name, _)| otherwise_declared.contains_key(name))
+                    .collect();
+            }
+            StatementKind::While { condition, body } => {
+                self.check_operand(condition, declared, line);
+
+                let mut body_declared = declared.clone();
+                self.check_block(body, &mut body_declared);
+
+                for (name, declared_line) in body_declared {
+                    if !declared.contains_key(&name) {
+                        self.problem(
+                            declared_line,
+                            &format!(
+                                "'{}' is declared inside STICK AROUND, which fails the second time round",
+                                name
+                            ),
+                        );
+                    }
+                }
+            }
+            StatementKind::Call {
+                target,
+                method,
+                arguments,
+            } => {
+                for argument in arguments {
+                    self.check_operand(argument, declared, line);
+                }
+                if let Some(target) = target {
+                    self.check_variable(target, declared, line);
+                }
+                self.check_call(method, arguments.len(), target.is_some(), line);
+            }
+            StatementKind::ReadInteger { target } => self.check_variable(target, declared, line),
+            StatementKind::Return(Some(o)) => self.check_operand(o, declared, line),
+            StatementKind::Return(None) => (),
+        }
+    }
+
+    fn check_call(&mut self, name: &str, arity: usize, uses_value: bool, line: usize) {
+        let Some(method) = self.program.methods.get(name) else {
+            self.problem(line, &format!("no method called '{}'", name));
+            return;
+        };
+
+        if method.parameters.len() != arity {
+            self.problem(
+                line,
+                &format!(
+                    "'{}' takes {} arguments but is given {}",
+                    name,
+                    method.parameters.len(),
+                    arity
+                ),
+            );
+        }
+
+        if uses_value && !method.returns_value {
+            self.problem(line, &format!("'{}' doesn't return a value", name));
+        }
+    }
+
+    fn check_expression(
+        &mut self,
+        expression: &Expression,
+        declared: &HashMap<String, usize>,
+        line: usize,
+    ) {
+        self.check_operand(&expression.first, declared, line);
+        for (_, operand) in expression.operations.iter() {
+            self.check_operand(operand, declared, line);
+        }
+    }
+
+    fn check_operand(&mut self, operand: &Operand, declared: &HashMap<String, usize>, line: usize) {
+        if let Operand::Variable(name) = operand {
+            self.check_variable(name, declared, line);
+        }
+    }
+
+    fn check_variable(&mut self, name: &str, declared: &HashMap<String, usize>, line: usize) {
+        if !declared.contains_key(name) {
+            self.problem(line, &format!("'{}' isn't declared", name));
+        }
+    }
+
+    fn problem(&mut self, line: usize, message: &str) {
+        self.problems.push(Problem {
+            line,
+            message: String::from(message),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check;
+
+    fn messages(source: &str) -> Vec<String> {
+        check(source)
+            .unwrap()
+            .iter()
+            .map(|p| p.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn finds_nothing_wrong_with_valid_program() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE x
+    YOU SET US UP 1
+    GET YOUR ASS TO MARS x
+    DO IT NOW doubleFUNC x
+YOU HAVE BEEN TERMINATED
+
+LISTEN TO ME VERY CAREFULLY doubleFUNC
+    I NEED YOUR CLOTHES YOUR BOOTS AND YOUR MOTORCYCLE n
+    GIVE THESE PEOPLE AIR
+    GET TO THE CHOPPER n
+    HERE IS MY INVITATION n
+    YOU'RE FIRED 2
+    ENOUGH TALK
+    I'LL BE BACK n
+HASTA LA VISTA, BABY";
+
+        assert!(messages(source).is_empty());
+    }
+
+    #[test]
+    fn finds_unbalanced_blocks() {
+        let source = "IT'S SHOWTIME
+    STICK AROUND @NO PROBLEMO
+        BECAUSE I'M GOING TO SAY PLEASE @I LIED
+        CHILL
+    YOU HAVE NO RESPECT FOR LOGIC
+    CHILL
+YOU HAVE BEEN TERMINATED";
+
+        assert_eq!(
+            messages(source),
+            vec![
+                "line 3: BECAUSE I'M GOING TO SAY PLEASE is never closed by YOU HAVE NO RESPECT FOR LOGIC",
+                "line 5: YOU HAVE NO RESPECT FOR LOGIC without BECAUSE I'M GOING TO SAY PLEASE",
+                "line 6: CHILL without STICK AROUND",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_undeclared_variables_bad_calls_and_unreachable_code() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE x
+    YOU SET US UP y
+    DO IT NOW missingFUNC
+    DO IT NOW printFUNC x x
+    GET YOUR ASS TO MARS x
+    DO IT NOW printFUNC x
+YOU HAVE BEEN TERMINATED
+
+LISTEN TO ME VERY CAREFULLY printFUNC
+    I NEED YOUR CLOTHES YOUR BOOTS AND YOUR MOTORCYCLE n
+    TALK TO THE HAND n
+    I'LL BE BACK
+    TALK TO THE HAND total
+HASTA LA VISTA, BABY";
+
+        assert_eq!(
+            messages(source),
+            vec![
+                "line 2: 'y' isn't declared",
+                "line 4: no method called 'missingFUNC'",
+                "line 5: 'printFUNC' takes 1 arguments but is given 2",
+                "line 6: 'printFUNC' doesn't return a value",
+                "line 14: unreachable after I'LL BE BACK on line 13",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_variables_declared_in_only_one_branch() {
+        let source = "IT'S SHOWTIME
+    BECAUSE I'M GOING TO SAY PLEASE @NO PROBLEMO
+        HEY CHRISTMAS TREE x
+        YOU SET US UP 1
+        HEY CHRISTMAS TREE both
+        YOU SET US UP 1
+    BULLSHIT
+        TALK TO THE HAND x
+        HEY CHRISTMAS TREE both
+        YOU SET US UP 2
+    YOU HAVE NO RESPECT FOR LOGIC
+    TALK TO THE HAND x
+    TALK TO THE HAND both
+YOU HAVE BEEN TERMINATED";
+
+        assert_eq!(
+            messages(source),
+            vec!["line 8: 'x' isn't declared", "line 12: 'x' isn't declared",]
+        );
+    }
+
+    #[test]
+    fn finds_declarations_inside_loops() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE n
+    YOU SET US UP 3
+    STICK AROUND n
+        HEY CHRISTMAS TREE inside
+        YOU SET US UP n
+        TALK TO THE HAND inside
+        GET TO THE CHOPPER n
+        HERE IS MY INVITATION n
+        GET DOWN 1
+        ENOUGH TALK
+    CHILL
+YOU HAVE BEEN TERMINATED";
+
+        assert_eq!(
+            messages(source),
+            vec!["line 5: 'inside' is declared inside STICK AROUND, which fails the second time round"]
+        );
+    }
+}
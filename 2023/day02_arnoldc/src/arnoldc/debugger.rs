@@ -0,0 +1,418 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+use common::core::{Error, Result};
+
+use super::interpreter::{run_observed, Control, Observer};
+use super::parser::{parse, Program, Statement, StatementKind};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Breakpoint {
+    /// Stop before running the statement on this source line.
+    Line(usize),
+    /// Stop after any statement that changes this variable.
+    Changed(String),
+}
+
+/// A statement that ran, with the variables in scope before and after it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Step {
+    pub line: usize,
+    pub source: String,
+    pub method: String,
+    pub before: BTreeMap<String, i32>,
+    pub after: BTreeMap<String, i32>,
+}
+
+impl Step {
+    /// Variables whose value differs after the statement, with the old and new value.
+    pub fn changes(&self) -> Vec<(&String, Option<i32>, i32)> {
+        self.after
+            .iter()
+            .filter(|(name, value)| self.before.get(*name) != Some(value))
+            .map(|(name, value)| (name, self.before.get(name).copied(), *value))
+            .collect()
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:>4} [{}] {}", self.line, self.method, self.source)?;
+        for (name, before, after) in self.changes() {
+            match before {
+                Some(b) => write!(f, "  {}: {} -> {}", name, b, after)?,
+                None => write!(f, "  {}: {}", name, after)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Stop {
+    pub breakpoint: Breakpoint,
+    pub line: usize,
+    pub variables: BTreeMap<String, i32>,
+}
+
+#[derive(Debug)]
+pub struct Trace {
+    pub steps: Vec<Step>,
+    pub output: Vec<String>,
+    pub stopped: Option<Stop>,
+    /// Why the program failed, if it did. The last step is the statement that failed.
+    pub error: Option<Error>,
+}
+
+/// Where two traces first disagree on the line run or the variables after it.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Divergence {
+    pub index: usize,
+    pub left: Option<Step>,
+    pub right: Option<Step>,
+}
+
+pub struct Debugger {
+    program: Program,
+    source: Vec<String>,
+    breakpoints: Vec<Breakpoint>,
+    skip_hits: usize,
+}
+
+impl Debugger {
+    pub fn new(source: &str) -> Result<Debugger> {
+        Ok(Debugger {
+            program: parse(source)?,
+            source: source.lines().map(|l| String::from(l.trim())).collect(),
+            breakpoints: vec![],
+            skip_hits: 0,
+        })
+    }
+
+    pub fn with_breakpoint(mut self, breakpoint: Breakpoint) -> Debugger {
+        self.breakpoints.push(breakpoint);
+        self
+    }
+
+    /// Run past the first `hits` breakpoint hits. Programs are deterministic for a given input,
+    /// so re-running with a higher count is how to continue from a stop.
+    pub fn skipping(mut self, hits: usize) -> Debugger {
+        self.skip_hits = hits;
+        self
+    }
+
+    /// Everything up to the end of the program, a breakpoint or a runtime error.
+    pub fn trace<I>(&self, input: I) -> Trace
+    where
+        I: IntoIterator<Item = i32>,
+    {
+        let mut recorder = Recorder {
+            debugger: self,
+            steps: vec![],
+            pending: vec![],
+            hits: 0,
+            stopped: None,
+        };
+
+        let (output, result) = run_observed(&self.program, input, &mut recorder);
+
+        Trace {
+            steps: recorder.steps,
+            output,
+            stopped: recorder.stopped,
+            error: result.err(),
+        }
+    }
+}
+
+pub fn diff(left: &Trace, right: &Trace) -> Option<Divergence> {
+    let length = usize::max(left.steps.len(), right.steps.len());
+    (0..length)
+        .find(|i| match (left.steps.get(*i), right.steps.get(*i)) {
+            (Some(l), Some(r)) => l.line != r.line || l.after != r.after,
+            _ => true,
+        })
+        .map(|index| Divergence {
+            index,
+            left: left.steps.get(index).cloned(),
+            right: right.steps.get(index).cloned(),
+        })
+}
+
+/// A statement that has started but not finished.
+struct Pending {
+    step: usize,
+    /// How many calls deep the statement is.
+    frame: usize,
+    is_call: bool,
+    /// The variables after the last statement nested in this one in the same frame, so a
+    /// loop or branch isn't seen to make the changes its body already made.
+    latest: BTreeMap<String, i32>,
+}
+
+struct Recorder<'d> {
+    debugger: &'d Debugger,
+    steps: Vec<Step>,
+    pending: Vec<Pending>,
+    hits: usize,
+    stopped: Option<Stop>,
+}
+
+impl Recorder<'_> {
+    fn hit(
+        &mut self,
+        breakpoint: &Breakpoint,
+        line: usize,
+        variables: &BTreeMap<String, i32>,
+    ) -> Control {
+        self.hits += 1;
+        if self.hits <= self.debugger.skip_hits {
+            return Control::Continue;
+        }
+
+        self.stopped = Some(Stop {
+            breakpoint: breakpoint.clone(),
+            line,
+            variables: variables.clone(),
+        });
+        Control::Stop
+    }
+}
+
+impl Observer for Recorder<'_> {
+    fn before(
+        &mut self,
+        statement: &Statement,
+        method: &str,
+        variables: &HashMap<String, i32>,
+    ) -> Control {
+        let variables: BTreeMap<String, i32> = variables.clone().into_iter().collect();
+
+        let breakpoint = self
+            .debugger
+            .breakpoints
+            .iter()
+            .find(|b| **b == Breakpoint::Line(statement.line));
+        if let Some(breakpoint) = breakpoint {
+            if self.hit(breakpoint, statement.line, &variables) == Control::Stop {
+                return Control::Stop;
+            }
+        }
+
+        let frame = match self.pending.last() {
+            Some(parent) if parent.is_call => parent.frame + 1,
+            Some(parent) => parent.frame,
+            None => 0,
+        };
+        self.pending.push(Pending {
+            step: self.steps.len(),
+            frame,
+            is_call: matches!(statement.kind, StatementKind::Call { .. }),
+            latest: variables.clone(),
+        });
+        self.steps.push(Step {
+            line: statement.line,
+            source: self
+                .debugger
+                .source
+                .get(statement.line - 1)
+                .cloned()
+                .unwrap_or_default(),
+            method: String::from(method),
+            before: variables.clone(),
+            after: variables,
+        });
+
+        Control::Continue
+    }
+
+    fn after(&mut self, _: &Statement, _: &str, variables: &HashMap<String, i32>) -> Control {
+        let pending = self.pending.pop().unwrap();
+        let step = &mut self.steps[pending.step];
+        step.after = variables.clone().into_iter().collect();
+
+        let line = step.line;
+        let after = step.after.clone();
+        let changed: Vec<&String> = after
+            .iter()
+            .filter(|(name, value)| pending.latest.get(*name) != Some(value))
+            .map(|(name, _)| name)
+            .collect();
+
+        if let Some(parent) = self.pending.last_mut() {
+            if parent.frame == pending.frame {
+                parent.latest = after.clone();
+            }
+        }
+
+        let breakpoint = self.debugger.breakpoints.iter().find(|b| match b {
+            Breakpoint::Changed(name) => changed.contains(&name),
+            Breakpoint::Line(_) => false,
+        });
+        match breakpoint {
+            Some(breakpoint) => self.hit(breakpoint, line, &after),
+            None => Control::Continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Breakpoint, Debugger};
+
+    const COUNTDOWN: &str = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE n
+    YOU SET US UP 0
+    GET YOUR ASS TO MARS n
+    DO IT NOW
+    I WANT TO ASK YOU A BUNCH OF QUESTIONS AND I WANT TO HAVE THEM ANSWERED IMMEDIATELY
+    STICK AROUND n
+        GET TO THE CHOPPER n
+        HERE IS MY INVITATION n
+        GET DOWN 1
+        ENOUGH TALK
+    CHILL
+    TALK TO THE HAND n
+YOU HAVE BEEN TERMINATED";
+
+    #[test]
+    fn can_trace_every_statement_with_variables() {
+        let debugger = Debugger::new(COUNTDOWN).unwrap();
+
+        let trace = debugger.trace([2]);
+
+        let lines: Vec<usize> = trace.steps.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![2, 4, 7, 8, 8, 13]);
+        assert_eq!(
+            trace.steps[3].to_string(),
+            "   8 [IT'S SHOWTIME] GET TO THE CHOPPER n  n: 2 -> 1"
+        );
+        assert_eq!(trace.output, vec!["0"]);
+        assert!(trace.stopped.is_none());
+    }
+
+    #[test]
+    fn can_stop_at_line() {
+        let debugger = Debugger::new(COUNTDOWN)
+            .unwrap()
+            .with_breakpoint(Breakpoint::Line(8));
+
+        let trace = debugger.trace([5]);
+
+        let stop = trace.stopped.unwrap();
+        assert_eq!(stop.line, 8);
+        assert_eq!(stop.variables["n"], 5);
+        assert!(trace.output.is_empty());
+    }
+
+    #[test]
+    fn can_continue_to_next_change_of_variable() {
+        let debugger = Debugger::new(COUNTDOWN)
+            .unwrap()
+            .with_breakpoint(Breakpoint::Changed(String::from("n")))
+            .skipping(3);
+
+        let trace = debugger.trace([5]);
+
+        let stop = trace.stopped.unwrap();
+        assert_eq!(stop.line, 8);
+        assert_eq!(stop.variables["n"], 3);
+    }
+
+    #[test]
+    fn can_find_where_two_runs_diverge() {
+        let counting_by_two = COUNTDOWN.replace("GET DOWN 1", "GET DOWN 2");
+        let input = [4];
+
+        let left = Debugger::new(COUNTDOWN).unwrap().trace(input);
+        let right = Debugger::new(&counting_by_two).unwrap().trace(input);
+
+        let divergence = diff(&left, &right).unwrap();
+        assert_eq!(divergence.index, 3);
+        assert_eq!(divergence.left.unwrap().after["n"], 3);
+        assert_eq!(divergence.right.unwrap().after["n"], 2);
+        assert!(diff(&left, &left).is_none());
+    }
+
+    #[test]
+    fn counts_a_change_inside_a_loop_once() {
+        let source = COUNTDOWN.replace(
+            "TALK TO THE HAND n",
+            "GET TO THE CHOPPER n\nHERE IS MY INVITATION 9\nENOUGH TALK",
+        );
+        let debugger = |hits| {
+            Debugger::new(&source)
+                .unwrap()
+                .with_breakpoint(Breakpoint::Changed(String::from("n")))
+                .skipping(hits)
+        };
+
+        let mut stops = vec![];
+        for hits in 0..10 {
+            match debugger(hits).trace([2]).stopped {
+                Some(stop) => stops.push((stop.line, stop.variables["n"])),
+                None => break,
+            }
+        }
+
+        // the loop on line 7 doesn't count again for the change its body made
+        assert_eq!(stops, vec![(2, 0), (4, 2), (8, 1), (8, 0), (13, 9)]);
+        let stop = debugger(1).trace([2]).stopped.unwrap();
+        assert_eq!((stop.line, stop.variables["n"]), (4, 2));
+    }
+
+    #[test]
+    fn stops_on_a_change_made_by_a_call() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE n
+    YOU SET US UP 0
+    GET YOUR ASS TO MARS n
+    DO IT NOW seven
+YOU HAVE BEEN TERMINATED
+
+LISTEN TO ME VERY CAREFULLY seven
+GIVE THESE PEOPLE AIR
+    HEY CHRISTMAS TREE n
+    YOU SET US UP 7
+    I'LL BE BACK n
+HASTA LA VISTA, BABY";
+        let debugger = Debugger::new(source)
+            .unwrap()
+            .with_breakpoint(Breakpoint::Changed(String::from("n")))
+            .skipping(2);
+
+        let trace = debugger.trace([]);
+
+        // declaring each n are the first two hits, then the call sets the caller's n
+        let stop = trace.stopped.unwrap();
+        assert_eq!((stop.line, stop.variables["n"]), (4, 7));
+    }
+
+    #[test]
+    fn keeps_the_trace_of_a_failing_program() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE n
+    YOU SET US UP 2
+    TALK TO THE HAND n
+    GET TO THE CHOPPER n
+    HERE IS MY INVITATION 10
+    HE HAD TO SPLIT 0
+    ENOUGH TALK
+    TALK TO THE HAND n
+YOU HAVE BEEN TERMINATED";
+        let debugger = Debugger::new(source).unwrap();
+
+        let trace = debugger.trace([]);
+
+        let lines: Vec<usize> = trace.steps.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![2, 4, 5]);
+        assert_eq!(trace.steps[2].changes(), vec![]);
+        assert_eq!(trace.output, vec!["2"]);
+        assert_eq!(
+            trace.error.unwrap().get_reason(),
+            "line 5: division by zero"
+        );
+        assert!(debugger.trace([]).stopped.is_none());
+    }
+}
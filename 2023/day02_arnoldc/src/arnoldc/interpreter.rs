@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+
+use common::core::{Error, Result};
+
+use super::lexer::Operand;
+use super::parser::{Expression, Method, Operator, Printable, Program, Statement, StatementKind};
+
+pub const MAIN: &str = "IT'S SHOWTIME";
+
+/// Run a program, answering each `I WANT TO ASK YOU A BUNCH OF QUESTIONS...` from `input`.
+/// Returns everything printed with `TALK TO THE HAND`, one entry per line.
+pub fn run<I>(program: &Program, input: I) -> Result<Vec<String>>
+where
+    I: IntoIterator<Item = i32>,
+{
+    let (output, result) = run_observed(program, input, &mut NoObserver);
+
+    result.map(|_| output)
+}
+
+/// Run a program, telling `observer` about each statement as it starts and finishes. Returns
+/// what was printed even if the program fails partway.
+pub fn run_observed<I>(
+    program: &Program,
+    input: I,
+    observer: &mut dyn Observer,
+) -> (Vec<String>, Result<()>)
+where
+    I: IntoIterator<Item = i32>,
+{
+    let mut interpreter = Interpreter {
+        program,
+        input: input.into_iter(),
+        output: vec![],
+        methods: vec![MAIN],
+        observer,
+    };
+
+    let mut variables = HashMap::new();
+    let result = interpreter.execute_block(&program.main, &mut variables);
+
+    (interpreter.output, result.map(|_| ()))
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Control {
+    Continue,
+    Stop,
+}
+
+pub trait Observer {
+    fn before(
+        &mut self,
+        statement: &Statement,
+        method: &str,
+        variables: &HashMap<String, i32>,
+    ) -> Control;
+
+    /// Called once the statement, and anything nested in it, has run.
+    fn after(
+        &mut self,
+        statement: &Statement,
+        method: &str,
+        variables: &HashMap<String, i32>,
+    ) -> Control;
+}
+
+struct NoObserver;
+
+impl Observer for NoObserver {
+    fn before(&mut self, _: &Statement, _: &str, _: &HashMap<String, i32>) -> Control {
+        Control::Continue
+    }
+
+    fn after(&mut self, _: &Statement, _: &str, _: &HashMap<String, i32>) -> Control {
+        Control::Continue
+    }
+}
+
+enum Flow {
+    Next,
+    Return(Option<i32>),
+    Stop,
+}
+
+struct Interpreter<'a, 'o, I: Iterator<Item = i32>> {
+    program: &'a Program,
+    input: I,
+    output: Vec<String>,
+    methods: Vec<&'a str>,
+    observer: &'o mut dyn Observer,
+}
+
+impl<'a, 'o, I: Iterator<Item = i32>> Interpreter<'a, 'o, I> {
+    fn execute_block(
+        &mut self,
+        statements: &'a [Statement],
+        variables: &mut HashMap<String, i32>,
+    ) -> Result<Flow> {
+        for statement in statements {
+            let method = *self.methods.last().unwrap();
+            if self.observer.before(statement, method, variables) == Control::Stop {
+                return Ok(Flow::Stop);
+            }
+
+            let flow = self.execute(statement, variables)?;
+            if let Flow::Stop = flow {
+                return Ok(flow);
+            }
+
+            if self.observer.after(statement, method, variables) == Control::Stop {
+                return Ok(Flow::Stop);
+            }
+
+            if let Flow::Return(_) = flow {
+                return Ok(flow);
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn execute(
+        &mut self,
+        statement: &'a Statement,
+        variables: &mut HashMap<String, i32>,
+    ) -> Result<Flow> {
+        let line = statement.line;
+        match &statement.kind {
+            StatementKind::Print(Printable::Text(s)) => self.output.push(s.clone()),
+            StatementKind::Print(Printable::Operand(o)) => {
+                let value = evaluate_operand(o, variables, line)?;
+                self.output.push(value.to_string());
+            }
+            StatementKind::Declare { name, value } => {
+                if variables.contains_key(name) {
+                    return Err(error(line, &format!("'{}' is already declared", name)));
+                }
+                let value = evaluate_operand(value, variables, line)?;
+                variables.insert(name.clone(), value);
+            }
+            StatementKind::Assign { name, expression } => {
+                let value = evaluate(expression, variables, line)?;
+                assign(variables, name, value, line)?;
+            }
+            StatementKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let branch = match evaluate_operand(condition, variables, line)? {
+                    0 => otherwise,
+                    _ => then,
+                };
+                return self.execute_block(branch, variables);
+            }
+            StatementKind::While { condition, body } => {
+                while evaluate_operand(condition, variables, line)? != 0 {
+                    let flow = self.execute_block(body, variables)?;
+                    if let Flow::Return(_) | Flow::Stop = flow {
+                        return Ok(flow);
+                    }
+                }
+            }
+            StatementKind::Call {
+                target,
+                method,
+                arguments,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|a| evaluate_operand(a, variables, line))
+                    .collect::<Result<Vec<i32>>>()?;
+                let value = match self.call(method, &arguments, line)? {
+                    Flow::Stop => return Ok(Flow::Stop),
+                    Flow::Return(value) => value,
+                    Flow::Next => None,
+                };
+
+                if let Some(target) = target {
+                    let value = value.ok_or_else(|| {
+                        error(line, &format!("'{}' doesn't return a value", method))
+                    })?;
+                    assign(variables, target, value, line)?;
+                }
+            }
+            StatementKind::ReadInteger { target } => {
+                let value = self
+                    .input
+                    .next()
+                    .ok_or_else(|| error(line, "ran out of input"))?;
+                assign(variables, target, value, line)?;
+            }
+            StatementKind::Return(value) => {
+                let value = match value {
+                    Some(o) => Some(evaluate_operand(o, variables, line)?),
+                    None => None,
+                };
+                return Ok(Flow::Return(value));
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn call(&mut self, name: &str, arguments: &[i32], line: usize) -> Result<Flow> {
+        let program = self.program;
+        let method: &'a Method = program
+            .methods
+            .get(name)
+            .ok_or_else(|| error(line, &format!("no method called '{}'", name)))?;
+
+        if method.parameters.len() != arguments.len() {
+            return Err(error(
+                line,
+                &format!(
+                    "'{}' takes {} arguments but was given {}",
+                    name,
+                    method.parameters.len(),
+                    arguments.len()
+                ),
+            ));
+        }
+
+        // Methods only see their own parameters and declarations
+        let mut variables: HashMap<String, i32> = method
+            .parameters
+            .iter()
+            .cloned()
+            .zip(arguments.iter().copied())
+            .collect();
+
+        self.methods.push(&method.name);
+        let flow = self.execute_block(&method.body, &mut variables)?;
+        self.methods.pop();
+
+        let value = match flow {
+            Flow::Stop => return Ok(Flow::Stop),
+            Flow::Return(value) => value,
+            Flow::Next => None,
+        };
+
+        if method.returns_value && value.is_none() {
+            return Err(error(
+                line,
+                &format!("'{}' finished without returning a value", name),
+            ));
+        }
+
+        Ok(Flow::Return(value))
+    }
+}
+
+pub fn evaluate(
+    expression: &Expression,
+    variables: &HashMap<String, i32>,
+    line: usize,
+) -> Result<i32> {
+    let mut value = evaluate_operand(&expression.first, variables, line)?;
+    for (operator, operand) in expression.operations.iter() {
+        let right = evaluate_operand(operand, variables, line)?;
+        value = apply(*operator, value, right).ok_or_else(|| error(line, "division by zero"))?;
+    }
+
+    Ok(value)
+}
+
+/// Operators work on Java ints, so arithmetic wraps and logic treats anything non-zero as true.
+pub fn apply(operator: Operator, left: i32, right: i32) -> Option<i32> {
+    let value = match operator {
+        Operator::Plus => left.wrapping_add(right),
+        Operator::Minus => left.wrapping_sub(right),
+        Operator::Multiply => left.wrapping_mul(right),
+        Operator::Divide => left.checked_div(right)?,
+        Operator::Modulo => left.checked_rem(right)?,
+        Operator::Equal => (left == right) as i32,
+        Operator::Greater => (left > right) as i32,
+        Operator::Or => (left != 0 || right != 0) as i32,
+        Operator::And => (left != 0 && right != 0) as i32,
+    };
+
+    Some(value)
+}
+
+fn evaluate_operand(
+    operand: &Operand,
+    variables: &HashMap<String, i32>,
+    line: usize,
+) -> Result<i32> {
+    match operand {
+        Operand::Number(n) => Ok(*n),
+        Operand::Variable(name) => variables
+            .get(name)
+            .copied()
+            .ok_or_else(|| error(line, &format!("'{}' isn't declared", name))),
+    }
+}
+
+fn assign(variables: &mut HashMap<String, i32>, name: &str, value: i32, line: usize) -> Result<()> {
+    match variables.get_mut(name) {
+        Some(v) => {
+            *v = value;
+            Ok(())
+        }
+        None => Err(error(line, &format!("'{}' isn't declared", name))),
+    }
+}
+
+fn error(line: usize, message: &str) -> Error {
+    Error::new(&format!("line {}: {}", line, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use crate::arnoldc::parser::parse;
+
+    #[test]
+    fn can_sum_numbers_from_input() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE count
+    YOU SET US UP 0
+    GET YOUR ASS TO MARS count
+    DO IT NOW
+    I WANT TO ASK YOU A BUNCH OF QUESTIONS AND I WANT TO HAVE THEM ANSWERED IMMEDIATELY
+    HEY CHRISTMAS TREE total
+    YOU SET US UP 0
+    GET YOUR ASS TO MARS total
+    DO IT NOW sumFUNC count
+    TALK TO THE HAND \"total:\"
+    TALK TO THE HAND total
+YOU HAVE BEEN TERMINATED
+
+LISTEN TO ME VERY CAREFULLY sumFUNC
+    I NEED YOUR CLOTHES YOUR BOOTS AND YOUR MOTORCYCLE count
+    GIVE THESE PEOPLE AIR
+    HEY CHRISTMAS TREE total
+    YOU SET US UP 0
+    HEY CHRISTMAS TREE number
+    YOU SET US UP 0
+    STICK AROUND count
+        GET YOUR ASS TO MARS number
+        DO IT NOW
+        I WANT TO ASK YOU A BUNCH OF QUESTIONS AND I WANT TO HAVE THEM ANSWERED IMMEDIATELY
+        GET TO THE CHOPPER total
+        HERE IS MY INVITATION total
+        GET UP number
+        ENOUGH TALK
+        GET TO THE CHOPPER count
+        HERE IS MY INVITATION count
+        GET DOWN 1
+        ENOUGH TALK
+    CHILL
+    I'LL BE BACK total
+HASTA LA VISTA, BABY";
+
+        let program = parse(source).unwrap();
+
+        let output = run(&program, [3, 10, 20, 12]).unwrap();
+
+        assert_eq!(output, vec!["total:", "42"]);
+    }
+
+    #[test]
+    fn methods_cant_see_callers_variables() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE secret
+    YOU SET US UP 7
+    DO IT NOW leakFUNC
+YOU HAVE BEEN TERMINATED
+
+LISTEN TO ME VERY CAREFULLY leakFUNC
+    TALK TO THE HAND secret
+HASTA LA VISTA, BABY";
+
+        let program = parse(source).unwrap();
+
+        let error = run(&program, []).unwrap_err();
+
+        assert_eq!(error.get_reason(), "line 8: 'secret' isn't declared");
+    }
+
+    #[test]
+    fn reports_running_out_of_input() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE number
+    YOU SET US UP 0
+    GET YOUR ASS TO MARS number
+    DO IT NOW
+    I WANT TO ASK YOU A BUNCH OF QUESTIONS AND I WANT TO HAVE THEM ANSWERED IMMEDIATELY
+YOU HAVE BEEN TERMINATED";
+
+        let program = parse(source).unwrap();
+
+        let error = run(&program, []).unwrap_err();
+
+        assert_eq!(error.get_reason(), "line 4: ran out of input");
+    }
+
+    #[test]
+    fn reports_declaring_a_variable_again() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE loops
+    YOU SET US UP 2
+    STICK AROUND loops
+        HEY CHRISTMAS TREE inside
+        YOU SET US UP 0
+        GET TO THE CHOPPER loops
+        HERE IS MY INVITATION loops
+        GET DOWN 1
+        ENOUGH TALK
+    CHILL
+YOU HAVE BEEN TERMINATED";
+
+        let program = parse(source).unwrap();
+
+        let error = run(&program, []).unwrap_err();
+
+        assert_eq!(error.get_reason(), "line 5: 'inside' is already declared");
+    }
+}
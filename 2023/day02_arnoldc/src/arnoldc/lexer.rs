@@ -0,0 +1,214 @@
+use common::core::{Error, Result};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Keyword {
+    Begin,
+    End,
+    Print,
+    DeclareInt,
+    SetInitialValue,
+    BeginAssign,
+    SetValue,
+    EndAssign,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    Equal,
+    Greater,
+    Or,
+    And,
+    If,
+    Else,
+    EndIf,
+    While,
+    EndWhile,
+    DeclareMethod,
+    MethodArgument,
+    NonVoidMethod,
+    EndMethod,
+    CallMethod,
+    AssignFromCall,
+    ReadInteger,
+    Return,
+}
+
+const KEYWORDS: [(&str, Keyword); 30] = [
+    ("IT'S SHOWTIME", Keyword::Begin),
+    ("YOU HAVE BEEN TERMINATED", Keyword::End),
+    ("TALK TO THE HAND", Keyword::Print),
+    ("HEY CHRISTMAS TREE", Keyword::DeclareInt),
+    ("YOU SET US UP", Keyword::SetInitialValue),
+    ("GET TO THE CHOPPER", Keyword::BeginAssign),
+    ("HERE IS MY INVITATION", Keyword::SetValue),
+    ("ENOUGH TALK", Keyword::EndAssign),
+    ("GET UP", Keyword::Plus),
+    ("GET DOWN", Keyword::Minus),
+    ("YOU'RE FIRED", Keyword::Multiply),
+    ("HE HAD TO SPLIT", Keyword::Divide),
+    ("I LET HIM GO", Keyword::Modulo),
+    ("YOU ARE NOT YOU YOU ARE ME", Keyword::Equal),
+    ("LET OFF SOME STEAM BENNET", Keyword::Greater),
+    ("CONSIDER THAT A DIVORCE", Keyword::Or),
+    ("KNOCK KNOCK", Keyword::And),
+    ("BECAUSE I'M GOING TO SAY PLEASE", Keyword::If),
+    ("BULLSHIT", Keyword::Else),
+    ("YOU HAVE NO RESPECT FOR LOGIC", Keyword::EndIf),
+    ("STICK AROUND", Keyword::While),
+    ("CHILL", Keyword::EndWhile),
+    ("LISTEN TO ME VERY CAREFULLY", Keyword::DeclareMethod),
+    (
+        "I NEED YOUR CLOTHES YOUR BOOTS AND YOUR MOTORCYCLE",
+        Keyword::MethodArgument,
+    ),
+    ("GIVE THESE PEOPLE AIR", Keyword::NonVoidMethod),
+    ("HASTA LA VISTA, BABY", Keyword::EndMethod),
+    ("DO IT NOW", Keyword::CallMethod),
+    ("GET YOUR ASS TO MARS", Keyword::AssignFromCall),
+    (
+        "I WANT TO ASK YOU A BUNCH OF QUESTIONS AND I WANT TO HAVE THEM ANSWERED IMMEDIATELY",
+        Keyword::ReadInteger,
+    ),
+    ("I'LL BE BACK", Keyword::Return),
+];
+
+const TRUE_MACRO: &str = "@NO PROBLEMO";
+const FALSE_MACRO: &str = "@I LIED";
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Operand {
+    Number(i32),
+    Variable(String),
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Argument {
+    Operand(Operand),
+    Text(String),
+}
+
+/// One line of source: ArnoldC has exactly one keyword phrase per line, followed by its arguments.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Token {
+    pub keyword: Keyword,
+    pub arguments: Vec<Argument>,
+    pub line: usize,
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let (phrase, keyword) = KEYWORDS
+            .iter()
+            .find(|(phrase, _)| starts_with_phrase(text, phrase))
+            .ok_or_else(|| Error::new(&format!("line {}: unknown statement '{}'", line, text)))?;
+
+        let arguments = tokenize_arguments(text[phrase.len()..].trim(), line)?;
+
+        tokens.push(Token {
+            keyword: *keyword,
+            arguments,
+            line,
+        });
+    }
+
+    Ok(tokens)
+}
+
+fn starts_with_phrase(text: &str, phrase: &str) -> bool {
+    match text.strip_prefix(phrase) {
+        Some(rest) => rest.is_empty() || rest.starts_with(char::is_whitespace),
+        None => false,
+    }
+}
+
+fn tokenize_arguments(text: &str, line: usize) -> Result<Vec<Argument>> {
+    if text.is_empty() {
+        return Ok(vec![]);
+    }
+
+    if let Some(quoted) = text.strip_prefix('"') {
+        return match quoted.strip_suffix('"') {
+            Some(s) => Ok(vec![Argument::Text(String::from(s))]),
+            None => Err(Error::new(&format!("line {}: unterminated string", line))),
+        };
+    }
+
+    if text == TRUE_MACRO {
+        return Ok(vec![Argument::Operand(Operand::Number(1))]);
+    }
+    if text == FALSE_MACRO {
+        return Ok(vec![Argument::Operand(Operand::Number(0))]);
+    }
+
+    text.split_whitespace()
+        .map(|word| tokenize_operand(word, line).map(Argument::Operand))
+        .collect()
+}
+
+fn tokenize_operand(word: &str, line: usize) -> Result<Operand> {
+    if word.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+        return word
+            .parse::<i32>()
+            .map(Operand::Number)
+            .map_err(|_| Error::new(&format!("line {}: bad number '{}'", line, word)));
+    }
+
+    if word.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Ok(Operand::Variable(String::from(word)));
+    }
+
+    Err(Error::new(&format!(
+        "line {}: bad operand '{}'",
+        line, word
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, Argument, Keyword, Operand};
+
+    #[test]
+    fn can_tokenize_lines_with_arguments() {
+        let source = "IT'S SHOWTIME\n\n    HEY CHRISTMAS TREE bagRed\n    YOU SET US UP @NO PROBLEMO\n    TALK TO THE HAND \"how many games?\"\n    DO IT NOW checkFUNC bagRed -3\nYOU HAVE BEEN TERMINATED";
+
+        let tokens = tokenize(source).unwrap();
+
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[1].keyword, Keyword::DeclareInt);
+        assert_eq!(tokens[1].line, 3);
+        assert_eq!(
+            tokens[2].arguments,
+            vec![Argument::Operand(Operand::Number(1))]
+        );
+        assert_eq!(
+            tokens[3].arguments,
+            vec![Argument::Text(String::from("how many games?"))]
+        );
+        assert_eq!(
+            tokens[4].arguments,
+            vec![
+                Argument::Operand(Operand::Variable(String::from("checkFUNC"))),
+                Argument::Operand(Operand::Variable(String::from("bagRed"))),
+                Argument::Operand(Operand::Number(-3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_line_of_unknown_statement() {
+        let error = tokenize("IT'S SHOWTIME\nGET TO THE PLANE x").unwrap_err();
+
+        assert_eq!(
+            error.get_reason(),
+            "line 2: unknown statement 'GET TO THE PLANE x'"
+        );
+    }
+}
@@ -0,0 +1,430 @@
+use std::collections::HashMap;
+
+use common::core::{Error, Result};
+
+use super::lexer::{tokenize, Argument, Keyword, Operand, Token};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Operator {
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    Equal,
+    Greater,
+    Or,
+    And,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Expression {
+    pub first: Operand,
+    pub operations: Vec<(Operator, Operand)>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Printable {
+    Text(String),
+    Operand(Operand),
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum StatementKind {
+    Print(Printable),
+    Declare {
+        name: String,
+        value: Operand,
+    },
+    Assign {
+        name: String,
+        expression: Expression,
+    },
+    If {
+        condition: Operand,
+        then: Vec<Statement>,
+        otherwise: Vec<Statement>,
+    },
+    While {
+        condition: Operand,
+        body: Vec<Statement>,
+    },
+    Call {
+        target: Option<String>,
+        method: String,
+        arguments: Vec<Operand>,
+    },
+    ReadInteger {
+        target: String,
+    },
+    Return(Option<Operand>),
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub line: usize,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Method {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub returns_value: bool,
+    pub body: Vec<Statement>,
+    pub line: usize,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Program {
+    pub main: Vec<Statement>,
+    pub methods: HashMap<String, Method>,
+}
+
+pub fn parse(source: &str) -> Result<Program> {
+    let tokens = tokenize(source)?;
+    Parser {
+        tokens,
+        position: 0,
+    }
+    .parse_program()
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn parse_program(&mut self) -> Result<Program> {
+        let mut main: Option<Vec<Statement>> = None;
+        let mut methods: HashMap<String, Method> = HashMap::new();
+
+        while let Some(token) = self.next() {
+            match token.keyword {
+                Keyword::Begin if main.is_none() => {
+                    main = Some(self.parse_block(&[Keyword::End])?.0);
+                }
+                Keyword::DeclareMethod => {
+                    let method = self.parse_method(&token)?;
+                    if methods.contains_key(&method.name) {
+                        return Err(error(&token, "method declared twice"));
+                    }
+                    methods.insert(method.name.clone(), method);
+                }
+                _ => return Err(error(&token, "expected IT'S SHOWTIME or a method")),
+            }
+        }
+
+        match main {
+            Some(main) => Ok(Program { main, methods }),
+            None => Err(Error::new("no IT'S SHOWTIME")),
+        }
+    }
+
+    fn parse_method(&mut self, declaration: &Token) -> Result<Method> {
+        let name = variable_argument(declaration)?;
+
+        let mut parameters = vec![];
+        while let Some(token) = self.peek_keyword(Keyword::MethodArgument) {
+            parameters.push(variable_argument(&token)?);
+            self.position += 1;
+        }
+
+        let returns_value = self.peek_keyword(Keyword::NonVoidMethod).is_some();
+        if returns_value {
+            self.position += 1;
+        }
+
+        let (body, _) = self.parse_block(&[Keyword::EndMethod])?;
+
+        Ok(Method {
+            name,
+            parameters,
+            returns_value,
+            body,
+            line: declaration.line,
+        })
+    }
+
+    /// Parse statements until one of the `terminators`, which is consumed and returned.
+    fn parse_block(&mut self, terminators: &[Keyword]) -> Result<(Vec<Statement>, Token)> {
+        let mut statements = vec![];
+        loop {
+            let token = match self.next() {
+                Some(t) => t,
+                None => {
+                    let line = self.tokens.last().map(|t| t.line).unwrap_or(0);
+                    return Err(Error::new(&format!(
+                        "line {}: expected {:?} before end of file",
+                        line, terminators
+                    )));
+                }
+            };
+
+            if terminators.contains(&token.keyword) {
+                return Ok((statements, token));
+            }
+
+            statements.push(self.parse_statement(token)?);
+        }
+    }
+
+    fn parse_statement(&mut self, token: Token) -> Result<Statement> {
+        let kind = match token.keyword {
+            Keyword::Print => match single_argument(&token)? {
+                Argument::Text(s) => StatementKind::Print(Printable::Text(s)),
+                Argument::Operand(o) => StatementKind::Print(Printable::Operand(o)),
+            },
+            Keyword::DeclareInt => {
+                let name = variable_argument(&token)?;
+                let value_token = self.expect(Keyword::SetInitialValue, &token)?;
+                StatementKind::Declare {
+                    name,
+                    value: operand_argument(&value_token)?,
+                }
+            }
+            Keyword::BeginAssign => {
+                let name = variable_argument(&token)?;
+                let first_token = self.expect(Keyword::SetValue, &token)?;
+                let mut expression = Expression {
+                    first: operand_argument(&first_token)?,
+                    operations: vec![],
+                };
+                loop {
+                    let next = self.next_or_error(&token)?;
+                    if next.keyword == Keyword::EndAssign {
+                        break;
+                    }
+
+                    let operator = match operator(next.keyword) {
+                        Some(o) => o,
+                        None => return Err(error(&next, "expected an operator or ENOUGH TALK")),
+                    };
+                    expression
+                        .operations
+                        .push((operator, operand_argument(&next)?));
+                }
+                StatementKind::Assign { name, expression }
+            }
+            Keyword::If => {
+                let condition = operand_argument(&token)?;
+                let (then, end) = self.parse_block(&[Keyword::Else, Keyword::EndIf])?;
+                let otherwise = if end.keyword == Keyword::Else {
+                    self.parse_block(&[Keyword::EndIf])?.0
+                } else {
+                    vec![]
+                };
+                StatementKind::If {
+                    condition,
+                    then,
+                    otherwise,
+                }
+            }
+            Keyword::While => {
+                let condition = operand_argument(&token)?;
+                let (body, _) = self.parse_block(&[Keyword::EndWhile])?;
+                StatementKind::While { condition, body }
+            }
+            Keyword::CallMethod => {
+                let (method, arguments) = call_arguments(&token)?;
+                StatementKind::Call {
+                    target: None,
+                    method,
+                    arguments,
+                }
+            }
+            Keyword::AssignFromCall => {
+                let target = variable_argument(&token)?;
+                let call = self.expect(Keyword::CallMethod, &token)?;
+                if call.arguments.is_empty() {
+                    self.expect(Keyword::ReadInteger, &call)?;
+                    StatementKind::ReadInteger { target }
+                } else {
+                    let (method, arguments) = call_arguments(&call)?;
+                    StatementKind::Call {
+                        target: Some(target),
+                        method,
+                        arguments,
+                    }
+                }
+            }
+            Keyword::Return => match token.arguments.len() {
+                0 => StatementKind::Return(None),
+                _ => StatementKind::Return(Some(operand_argument(&token)?)),
+            },
+            _ => return Err(error(&token, "unexpected statement")),
+        };
+
+        Ok(Statement {
+            kind,
+            line: token.line,
+        })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn next_or_error(&mut self, after: &Token) -> Result<Token> {
+        self.next()
+            .ok_or_else(|| error(after, "unexpected end of file"))
+    }
+
+    fn peek_keyword(&self, keyword: Keyword) -> Option<Token> {
+        self.tokens
+            .get(self.position)
+            .filter(|t| t.keyword == keyword)
+            .cloned()
+    }
+
+    fn expect(&mut self, keyword: Keyword, after: &Token) -> Result<Token> {
+        let token = self.next_or_error(after)?;
+        if token.keyword != keyword {
+            return Err(error(&token, &format!("expected {:?}", keyword)));
+        }
+
+        Ok(token)
+    }
+}
+
+pub fn operator(keyword: Keyword) -> Option<Operator> {
+    match keyword {
+        Keyword::Plus => Some(Operator::Plus),
+        Keyword::Minus => Some(Operator::Minus),
+        Keyword::Multiply => Some(Operator::Multiply),
+        Keyword::Divide => Some(Operator::Divide),
+        Keyword::Modulo => Some(Operator::Modulo),
+        Keyword::Equal => Some(Operator::Equal),
+        Keyword::Greater => Some(Operator::Greater),
+        Keyword::Or => Some(Operator::Or),
+        Keyword::And => Some(Operator::And),
+        _ => None,
+    }
+}
+
+fn error(token: &Token, message: &str) -> Error {
+    Error::new(&format!("line {}: {}", token.line, message))
+}
+
+fn single_argument(token: &Token) -> Result<Argument> {
+    match token.arguments.as_slice() {
+        [argument] => Ok(argument.clone()),
+        _ => Err(error(token, "expected one argument")),
+    }
+}
+
+fn operand_argument(token: &Token) -> Result<Operand> {
+    match single_argument(token)? {
+        Argument::Operand(o) => Ok(o),
+        Argument::Text(_) => Err(error(token, "expected a number or variable")),
+    }
+}
+
+fn variable_argument(token: &Token) -> Result<String> {
+    match operand_argument(token)? {
+        Operand::Variable(name) => Ok(name),
+        Operand::Number(_) => Err(error(token, "expected a name")),
+    }
+}
+
+fn call_arguments(token: &Token) -> Result<(String, Vec<Operand>)> {
+    let mut operands = token.arguments.iter().map(|a| match a {
+        Argument::Operand(o) => Ok(o.clone()),
+        Argument::Text(_) => Err(error(token, "can't pass a string to a method")),
+    });
+
+    let method = match operands.next() {
+        Some(Ok(Operand::Variable(name))) => name,
+        _ => return Err(error(token, "expected a method name")),
+    };
+
+    Ok((method, operands.collect::<Result<Vec<Operand>>>()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Expression, Operator, StatementKind};
+    use crate::arnoldc::lexer::Operand;
+
+    #[test]
+    fn can_parse_method_with_loop_and_condition() {
+        let source = "IT'S SHOWTIME
+    HEY CHRISTMAS TREE total
+    YOU SET US UP 0
+    GET YOUR ASS TO MARS total
+    DO IT NOW countFUNC 3
+YOU HAVE BEEN TERMINATED
+
+LISTEN TO ME VERY CAREFULLY countFUNC
+    I NEED YOUR CLOTHES YOUR BOOTS AND YOUR MOTORCYCLE limit
+    GIVE THESE PEOPLE AIR
+    STICK AROUND limit
+        GET TO THE CHOPPER limit
+        HERE IS MY INVITATION limit
+        GET DOWN 1
+        ENOUGH TALK
+    CHILL
+    BECAUSE I'M GOING TO SAY PLEASE limit
+        I'LL BE BACK 1
+    BULLSHIT
+        I'LL BE BACK 0
+    YOU HAVE NO RESPECT FOR LOGIC
+HASTA LA VISTA, BABY";
+
+        let program = parse(source).unwrap();
+
+        assert_eq!(program.main.len(), 2);
+        assert_eq!(
+            program.main[1].kind,
+            StatementKind::Call {
+                target: Some(String::from("total")),
+                method: String::from("countFUNC"),
+                arguments: vec![Operand::Number(3)],
+            }
+        );
+
+        let method = &program.methods["countFUNC"];
+        assert_eq!(method.parameters, vec![String::from("limit")]);
+        assert!(method.returns_value);
+        assert_eq!(method.body.len(), 2);
+
+        let StatementKind::While { body, .. } = &method.body[0].kind else {
+            panic!("expected a loop");
+        };
+        assert_eq!(body[0].line, 12);
+        assert_eq!(
+            body[0].kind,
+            StatementKind::Assign {
+                name: String::from("limit"),
+                expression: Expression {
+                    first: Operand::Variable(String::from("limit")),
+                    operations: vec![(Operator::Minus, Operand::Number(1))],
+                },
+            }
+        );
+
+        let StatementKind::If {
+            then, otherwise, ..
+        } = &method.body[1].kind
+        else {
+            panic!("expected a condition");
+        };
+        assert_eq!(
+            then[0].kind,
+            StatementKind::Return(Some(Operand::Number(1)))
+        );
+        assert_eq!(otherwise[0].line, 20);
+    }
+
+    #[test]
+    fn reports_unclosed_loop() {
+        let source = "IT'S SHOWTIME\nSTICK AROUND @NO PROBLEMO\nYOU HAVE BEEN TERMINATED";
+
+        let error = parse(source).unwrap_err();
+
+        assert_eq!(error.get_reason(), "line 3: unexpected statement");
+    }
+}
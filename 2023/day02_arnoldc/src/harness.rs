@@ -0,0 +1,203 @@
+use std::fmt;
+
+use common::core::{Error, Result};
+use common::random::Random;
+use day02::block_game::{Bag, Game, Round, COLOURS};
+
+use crate::arnoldc::bytecode::{compile, execute, Bytecode};
+use crate::arnoldc::parser::parse;
+
+/// The bag `main.arnoldc` has hard-coded.
+pub fn bag() -> Bag {
+    Bag::new([("red", 12), ("green", 13), ("blue", 14)])
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Check {
+    Possible,
+    Power,
+}
+
+/// A game where the ArnoldC programs disagree with `day02`.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Mismatch {
+    pub id: i32,
+    pub check: Check,
+    pub expected: i32,
+    pub actual: i32,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = |answer: i32| match answer {
+            0 => "not possible",
+            _ => "possible",
+        };
+
+        match self.check {
+            Check::Possible => write!(
+                f,
+                "game {}: day02 says {} but ArnoldC says {} (printed {})",
+                self.id,
+                verdict(self.expected),
+                verdict(self.actual),
+                self.actual
+            ),
+            Check::Power => write!(
+                f,
+                "game {}: minimal bag power is {} in day02 but {} in ArnoldC",
+                self.id, self.expected, self.actual
+            ),
+        }
+    }
+}
+
+/// Runs both ArnoldC programs one game at a time, so a wrong total can be pinned on the games
+/// that caused it.
+pub struct Harness {
+    bag: Bag,
+    possible: Bytecode,
+    power: Bytecode,
+}
+
+impl Harness {
+    pub fn new(possible_source: &str, power_source: &str) -> Result<Self> {
+        Ok(Harness {
+            bag: bag(),
+            possible: compile(&parse(possible_source)?)?,
+            power: compile(&parse(power_source)?)?,
+        })
+    }
+
+    pub fn compare(&self, games: &[Game]) -> Result<Vec<Mismatch>> {
+        let mut mismatches = vec![];
+
+        for game in games {
+            let input = to_input(std::slice::from_ref(game));
+
+            let expected = match self.bag.is_game_possible(game) {
+                true => game.id,
+                false => 0,
+            };
+            let actual = answer(&self.possible, &input, game.id)?;
+            if actual != expected {
+                mismatches.push(Mismatch {
+                    id: game.id,
+                    check: Check::Possible,
+                    expected,
+                    actual,
+                });
+            }
+
+            let expected = Bag::create_minimal_bag(game).get_power();
+            let actual = answer(&self.power, &input, game.id)?;
+            if actual != expected {
+                mismatches.push(Mismatch {
+                    id: game.id,
+                    check: Check::Power,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        Ok(mismatches)
+    }
+}
+
+fn answer(bytecode: &Bytecode, input: &[i32], id: i32) -> Result<i32> {
+    let run = execute(bytecode, input.iter().copied())
+        .map_err(|e| Error::new(&format!("game {}: {}", id, e.get_reason())))?;
+
+    run.output
+        .last()
+        .and_then(|line| line.parse().ok())
+        .ok_or_else(|| Error::new(&format!("game {}: program didn't print a number", id)))
+}
+
+/// The numbers the ArnoldC programs ask for, in the order they ask for them.
+pub fn to_input(games: &[Game]) -> Vec<i32> {
+    let mut output = vec![games.len() as i32];
+
+    for game in games {
+        output.push(game.id);
+        output.push(game.rounds.len() as i32);
+
+        for round in game.rounds.iter() {
+            output.extend(COLOURS.map(|colour| round.count(colour)));
+        }
+    }
+
+    output
+}
+
+/// Games numbered from `first_id`, with up to six rounds of up to twenty cubes per colour,
+/// which straddles the bag's limits. A reported game can be produced again from the seed.
+pub fn generate_games(random: &mut Random, first_id: i32, count: usize) -> Vec<Game> {
+    (0..count as i32)
+        .map(|i| Game {
+            id: first_id + i,
+            rounds: (0..1 + random.next(6))
+                .map(|_| Round::new(COLOURS.map(|colour| (colour, random.next(21) as i32))))
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use day02::block_game::{deserialize_game_line, COLOURS};
+
+    use common::random::Random;
+
+    use super::{generate_games, Check, Harness, Mismatch};
+
+    fn harness(possible_source: &str, power_source: &str) -> Harness {
+        Harness::new(possible_source, power_source).unwrap()
+    }
+
+    fn sources() -> (String, String) {
+        (
+            read_to_string("./main.arnoldc").unwrap(),
+            read_to_string("./part_2.arnoldc").unwrap(),
+        )
+    }
+
+    #[test]
+    fn agrees_with_day02_on_random_games() {
+        let (possible, power) = sources();
+        let games = generate_games(&mut Random::new(2023), 1, 300);
+
+        let mismatches = harness(&possible, &power).compare(&games).unwrap();
+
+        assert_eq!(mismatches, vec![]);
+    }
+
+    #[test]
+    fn reports_games_where_the_programs_disagree() {
+        let (possible, power) = sources();
+        let smaller_bag = possible.replacen("YOU SET US UP  14", "YOU SET US UP  13", 1);
+        let games = vec![
+            deserialize_game_line("Game 1: 14 blue; 1 red", &COLOURS).unwrap(),
+            deserialize_game_line("Game 2: 13 blue, 2 red", &COLOURS).unwrap(),
+        ];
+
+        let mismatches = harness(&smaller_bag, &power).compare(&games).unwrap();
+
+        assert_eq!(
+            mismatches,
+            vec![Mismatch {
+                id: 1,
+                check: Check::Possible,
+                expected: 1,
+                actual: 0,
+            }]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "game 1: day02 says possible but ArnoldC says not possible (printed 0)"
+        );
+    }
+}
@@ -1,148 +1,228 @@
-use std::{
-    io::{BufRead, BufReader, Write},
-    process::{Command, Stdio},
-    thread, time,
-};
-
+use arnoldc::bytecode::{compile, execute, Run};
+use arnoldc::checker::check;
+use arnoldc::debugger::{diff, Breakpoint, Debugger};
+use arnoldc::interpreter;
+use arnoldc::parser::parse;
+use common::core::{Error, Result};
 use common::file;
+use common::random::Random;
+use day02::block_game::{deserialize_games, COLOURS};
+use harness::{generate_games, to_input, Harness};
+use std::{env, fs::read_to_string, time::Instant};
 
-fn main() {
-    let mut console = Command::new("conhost.exe")
-        .stdout(Stdio::piped())
-        .stdin(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .unwrap();
-
-    let console_stdin = console.stdin.as_mut().unwrap();
-    // console_stdin
-    //     .write(b"java -jar ArnoldC.jar main.arnoldc \r\n")
-    //     .unwrap();
-    // console_stdin
-    //     .write(b"java -jar ArnoldC.jar part_2.arnoldc \r\n")
-    //     .unwrap();
-
-    //console_stdin.write(b"java main  \r\n").unwrap(); // part 1
-    console_stdin.write(b"java part_2  \r\n").unwrap(); // part 2
-    thread::sleep(time::Duration::from_millis(1000));
+mod arnoldc;
+mod harness;
 
+fn main() {
     let inputs = get_arnolds_input("./input.txt");
-    //let inputs = get_arnolds_input("./example.txt");
 
-    for input in inputs {
-        thread::sleep(time::Duration::from_millis(50));
-        console_stdin
-            .write(format!("{} \r\n", input).as_bytes())
-            .unwrap();
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|a| a.as_str()) {
+        Some("trace") => return trace(&args[2], args.get(3), args.get(4), &inputs),
+        Some("diff") => return trace_diff(&args[2], &args[3], &inputs),
+        Some("check") => return check_files(&args[2..]),
+        Some("bench") => return bench(args.get(2), &inputs),
+        Some("compare") => return compare_with_day02(args.get(2), args.get(3)),
+        _ => (),
     }
 
-    thread::sleep(time::Duration::from_millis(1000));
-    console_stdin.write(b"exit  \r\n").unwrap();
-    console.wait().unwrap();
-
-    let stdout = console.stdout.as_mut().unwrap();
-    let out_reader = BufReader::new(stdout);
-    for line in out_reader.lines() {
-        println!("{}", line.unwrap());
+    for (part, filename) in [(1, "./main.arnoldc"), (2, "./part_2.arnoldc")] {
+        let run = run_compiled(filename, &inputs).unwrap();
+        println!(
+            "part {}: {} ({} instructions)",
+            part,
+            run.output.last().unwrap(),
+            run.instructions
+        );
     }
-
-    let stderr = console.stderr.as_mut().unwrap();
-    let err_reader = BufReader::new(stderr);
-    for line in err_reader.lines() {
-        println!("{}", line.unwrap());
-    }
-
-    // todo this gave 1830 as the answer, which is wrong
 }
 
-fn get_arnolds_input(filename: &str) -> Vec<i32> {
-    let file = String::from(filename);
-    let lines = file::read_lines(&file).unwrap();
-
-    let games = block_game::deserialize_games(&lines);
-
-    let mut output = Vec::<i32>::new();
-    output.push(games.len() as i32);
+/// Time the tree-walking interpreter against the bytecode VM over repeated runs.
+fn bench(runs: Option<&String>, inputs: &[i32]) {
+    let runs: u32 = runs.map(|r| r.parse().unwrap()).unwrap_or(1000);
 
-    for game in games {
-        output.push(game.id);
-        output.push(game.rounds.len() as i32);
+    for filename in ["./main.arnoldc", "./part_2.arnoldc"] {
+        let source = read_to_string(filename).unwrap();
+        let program = parse(&source).unwrap();
+        let bytecode = compile(&program).unwrap();
 
-        for round in game.rounds {
-            output.push(round.red);
-            output.push(round.green);
-            output.push(round.blue);
+        let start = Instant::now();
+        for _ in 0..runs {
+            interpreter::run(&program, inputs.iter().copied()).unwrap();
         }
+        let interpreted = start.elapsed();
+
+        let start = Instant::now();
+        let mut instructions = 0;
+        for _ in 0..runs {
+            instructions += execute(&bytecode, inputs.iter().copied())
+                .unwrap()
+                .instructions;
+        }
+        let compiled = start.elapsed();
+
+        println!(
+            "{}: interpreter {:?}/run, vm {:?}/run, {} instructions/run",
+            filename,
+            interpreted / runs,
+            compiled / runs,
+            instructions / runs as u64
+        );
     }
+}
 
-    return output;
+/// Compare the ArnoldC verdicts and powers with `day02`, game by game, on the puzzle input
+/// followed by some random games.
+fn compare_with_day02(count: Option<&String>, seed: Option<&String>) {
+    let count: usize = count.map(|c| c.parse().unwrap()).unwrap_or(1000);
+    let seed: u64 = seed.map(|s| s.parse().unwrap()).unwrap_or(2023);
+
+    let harness = Harness::new(
+        &read_to_string("./main.arnoldc").unwrap(),
+        &read_to_string("./part_2.arnoldc").unwrap(),
+    )
+    .unwrap();
+
+    let file = String::from("./input.txt");
+    let mut games = deserialize_games(&file::read_lines(&file).unwrap(), &COLOURS).unwrap();
+    let first_random_id = games.iter().map(|g| g.id).max().unwrap_or(0) + 1;
+    games.extend(generate_games(
+        &mut Random::new(seed),
+        first_random_id,
+        count,
+    ));
+
+    let mismatches = harness.compare(&games).unwrap();
+    for mismatch in mismatches.iter() {
+        println!("{}", mismatch);
+    }
+    println!(
+        "{} games compared (random ones from id {}, seed {}), {} mismatches",
+        games.len(),
+        first_random_id,
+        seed,
+        mismatches.len()
+    );
 }
 
-mod block_game {
-    pub struct Game {
-        pub id: i32,
-        pub rounds: Vec<Round>,
+fn check_files(filenames: &[String]) {
+    for filename in filenames {
+        let source = read_to_string(filename).unwrap();
+        for problem in check(&source).unwrap() {
+            println!("{}:{}", filename, problem);
+        }
     }
+}
 
-    pub struct Round {
-        pub red: i32,
-        pub green: i32,
-        pub blue: i32,
+/// Print every statement run, stopping at a breakpoint on a line number or a variable name
+/// once it has been hit `skip` times.
+fn trace(filename: &str, breakpoint: Option<&String>, skip: Option<&String>, inputs: &[i32]) {
+    let source = read_to_string(filename).unwrap();
+    let mut debugger = Debugger::new(&source).unwrap();
+    if let Some(breakpoint) = breakpoint {
+        debugger = debugger.with_breakpoint(match breakpoint.parse::<usize>() {
+            Ok(line) => Breakpoint::Line(line),
+            Err(_) => Breakpoint::Changed(breakpoint.clone()),
+        });
+    }
+    if let Some(skip) = skip {
+        debugger = debugger.skipping(skip.parse().unwrap());
     }
 
-    pub struct Bag {
-        pub red: i32,
-        pub green: i32,
-        pub blue: i32,
+    let trace = debugger.trace(inputs.iter().copied());
+    for step in trace.steps.iter() {
+        println!("{}", step);
     }
 
-    pub fn deserialize_games(lines: &Vec<String>) -> Vec<Game> {
-        let games: Vec<Game> = lines.iter().map(|l| deserialize_game_line(l)).collect();
+    if let Some(last) = trace.output.last() {
+        println!("last printed: {}", last);
+    }
 
-        return games;
+    if let Some(stop) = trace.stopped {
+        println!(
+            "stopped at line {} on {:?}: {:?}",
+            stop.line, stop.breakpoint, stop.variables
+        );
     }
 
-    pub fn deserialize_game_line(line: &str) -> Game {
-        let parts: Vec<&str> = line.split(":").collect();
-        let mut parts_iter = parts.iter();
+    if let Some(error) = trace.error {
+        println!("failed: {}", error.get_reason());
+    }
+}
 
-        let id_part = parts_iter.next().unwrap();
-        let id = id_part
-            .split_whitespace()
-            .into_iter()
-            .nth(1)
+fn trace_diff(left: &str, right: &str, inputs: &[i32]) {
+    let trace_file = |filename: &str| {
+        let source = read_to_string(filename).unwrap();
+        Debugger::new(&source)
             .unwrap()
-            .parse::<i32>()
-            .unwrap();
-        let round_parts: Vec<&str> = parts_iter.next().unwrap().split(";").collect();
+            .trace(inputs.iter().copied())
+    };
+
+    match diff(&trace_file(left), &trace_file(right)) {
+        Some(divergence) => {
+            println!("runs diverge at step {}", divergence.index);
+            println!("< {:?}", divergence.left.map(|s| s.to_string()));
+            println!("> {:?}", divergence.right.map(|s| s.to_string()));
+        }
+        None => println!("runs are identical"),
+    }
+}
+
+/// Compile an ArnoldC file and run it on the VM, returning everything it printed and how many
+/// instructions that took.
+fn run_compiled(filename: &str, inputs: &[i32]) -> Result<Run> {
+    let source = read_to_string(filename).map_err(|_| Error::new("can't read file"))?;
+    let bytecode = compile(&parse(&source)?)?;
 
-        let rounds: Vec<Round> = round_parts.iter().map(|p| deserialize_round(p)).collect();
+    execute(&bytecode, inputs.iter().copied())
+}
 
-        return Game {
-            id: id,
-            rounds: rounds,
-        };
+fn get_arnolds_input(filename: &str) -> Vec<i32> {
+    let file = String::from(filename);
+    let lines = file::read_lines(&file).unwrap();
+
+    to_input(&deserialize_games(&lines, &COLOURS).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{check, get_arnolds_input, run_compiled};
+    use std::fs::read_to_string;
+    use test_case::test_case;
+
+    #[test_case("./main.arnoldc")]
+    #[test_case("./part_2.arnoldc")]
+    fn arnoldc_sources_pass_checks(filename: &str) {
+        let source = read_to_string(filename).unwrap();
+
+        let problems = check(&source).unwrap();
+
+        assert!(
+            problems.is_empty(),
+            "{}",
+            problems
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join("\n")
+        );
     }
 
-    fn deserialize_round(text: &str) -> Round {
-        let parts: Vec<&str> = text.split(",").collect();
+    #[test]
+    fn can_find_sum_of_possible_game_ids_for_example_file() {
+        let inputs = get_arnolds_input("./example.txt");
+
+        let run = run_compiled("./main.arnoldc", &inputs).unwrap();
 
-        let (mut red, mut green, mut blue) = (0, 0, 0);
-        for part in parts.iter() {
-            let words: Vec<&str> = part.split_whitespace().collect();
-            let mut words_iter = words.iter();
+        assert_eq!(run.output.last().unwrap(), "8");
+    }
 
-            let number = words_iter.next().unwrap().parse::<i32>().unwrap();
-            let second_word = words_iter.next().unwrap();
+    #[test]
+    fn can_find_sum_of_minimal_bag_powers_for_example_file() {
+        let inputs = get_arnolds_input("./example.txt");
 
-            match second_word {
-                &"red" => red += number,
-                &"green" => green += number,
-                &"blue" => blue += number,
-                _ => panic!(),
-            }
-        }
+        let run = run_compiled("./part_2.arnoldc", &inputs).unwrap();
 
-        return Round { red, green, blue };
+        assert_eq!(run.output.last().unwrap(), "2286");
     }
 }
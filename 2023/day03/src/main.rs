@@ -1,11 +1,22 @@
 use crate::engine_schematic::Schematic;
 use common::file;
+use std::env;
 
 fn main() {
     let file = String::from("./input.txt");
     let lines = file::read_lines(&file).unwrap();
 
     let schematic = Schematic::deserialize(&lines);
+
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|a| a.as_str()) {
+        Some("json") => return println!("{}", schematic.report().to_json()),
+        Some("grid") => return print!("{}", schematic.scene().to_ansi()),
+        Some("report") => return print_report(&schematic),
+        Some(path) => return schematic.scene().write(path).unwrap(),
+        None => (),
+    }
+
     let sum = schematic.sum_part_numbers();
     let sum_2 = schematic.sum_part_numbers_2();
 
@@ -13,186 +24,431 @@ fn main() {
     println!("Part two answer is  {}", sum_2);
 }
 
+fn print_report(schematic: &Schematic) {
+    let report = schematic.report();
+
+    for number in report.numbers.iter().filter(|n| n.symbols.is_empty()) {
+        println!(
+            "{} at ({}, {}) touches no symbol",
+            number.number.value, number.number.start.x, number.number.start.y
+        );
+    }
+
+    for symbol in schematic.symbols_touching_at_least(3) {
+        println!(
+            "{} at ({}, {}) touches {} numbers",
+            symbol.symbol.symbol,
+            symbol.symbol.at.x,
+            symbol.symbol.at.y,
+            symbol.numbers.len()
+        );
+    }
+
+    for total in report.totals.iter() {
+        println!(
+            "{}: {} symbols, part numbers sum to {}, gear ratios sum to {}",
+            total.symbol, total.count, total.part_number_sum, total.gear_ratio_sum
+        );
+    }
+}
+
 mod engine_schematic {
+    use common::render::{Colour, Layer, Scene};
     use regex::Regex;
-    use std::collections::{HashMap, HashSet};
+    use std::collections::{BTreeMap, HashMap};
 
+    /// Numbers and symbols, each indexed by every cell it covers so neighbours are found by
+    /// looking up the cells around something rather than searching everything.
     pub struct Schematic {
-        numbers: HashMap<Coordinate, i32>,
-        symbols: HashMap<Coordinate, String>,
+        grid: Vec<Vec<char>>,
+        numbers: Vec<Number>,
+        symbols: Vec<Symbol>,
+        number_at: HashMap<Coordinate, usize>,
+        symbol_at: HashMap<Coordinate, usize>,
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+    pub struct Coordinate {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    /// A number and where it's written: `length` cells rightwards from `start`.
+    #[derive(PartialEq, Eq, Clone, Debug)]
+    pub struct Number {
+        pub value: i32,
+        pub start: Coordinate,
+        pub length: i32,
     }
 
-    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
-    struct Coordinate {
-        x: i32,
-        y: i32,
+    impl Number {
+        fn cells(&self) -> impl Iterator<Item = Coordinate> + '_ {
+            (0..self.length).map(|i| Coordinate {
+                x: self.start.x + i,
+                y: self.start.y,
+            })
+        }
+
+        /// The cells touching the number, including diagonally.
+        fn border(&self) -> impl Iterator<Item = Coordinate> + '_ {
+            let (y, left_x, right_x) = (self.start.y, self.start.x - 1, self.start.x + self.length);
+
+            (left_x..=right_x)
+                .flat_map(move |x| [Coordinate { x, y: y - 1 }, Coordinate { x, y: y + 1 }])
+                .chain([Coordinate { x: left_x, y }, Coordinate { x: right_x, y }])
+        }
+    }
+
+    #[derive(PartialEq, Eq, Clone, Debug)]
+    pub struct Symbol {
+        pub symbol: String,
+        pub at: Coordinate,
+    }
+
+    impl Symbol {
+        fn border(&self) -> impl Iterator<Item = Coordinate> + '_ {
+            (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                .filter(|d| *d != (0, 0))
+                .map(|(dx, dy)| Coordinate {
+                    x: self.at.x + dx,
+                    y: self.at.y + dy,
+                })
+        }
     }
 
     impl Schematic {
-        pub fn deserialize(lines: &Vec<String>) -> Self {
-            let mut numbers = HashMap::<Coordinate, i32>::new();
-            let mut symbols = HashMap::<Coordinate, String>::new();
+        pub fn deserialize(lines: &[String]) -> Self {
+            let mut schematic = Schematic {
+                grid: lines.iter().map(|l| l.chars().collect()).collect(),
+                numbers: vec![],
+                symbols: vec![],
+                number_at: HashMap::new(),
+                symbol_at: HashMap::new(),
+            };
 
             let num_regex = Regex::new(r"\d+").unwrap();
             let symbol_regex = Regex::new(r#"[^\d.]"#).unwrap();
 
             for (y, line) in lines.iter().enumerate() {
                 for num_match in num_regex.find_iter(line) {
-                    let match_text = &line[num_match.start()..num_match.end()];
-                    numbers.insert(
-                        Coordinate {
+                    let number = Number {
+                        value: num_match.as_str().parse::<i32>().unwrap(),
+                        start: Coordinate {
                             x: num_match.start() as i32,
                             y: y as i32,
                         },
-                        match_text.parse::<i32>().unwrap(),
-                    );
+                        length: num_match.len() as i32,
+                    };
+
+                    for cell in number.cells() {
+                        schematic.number_at.insert(cell, schematic.numbers.len());
+                    }
+                    schematic.numbers.push(number);
                 }
                 for symbol_match in symbol_regex.find_iter(line) {
-                    let match_text = &line[symbol_match.start()..symbol_match.end()];
-                    symbols.insert(
-                        Coordinate {
+                    let symbol = Symbol {
+                        symbol: symbol_match.as_str().to_string(),
+                        at: Coordinate {
                             x: symbol_match.start() as i32,
                             y: y as i32,
                         },
-                        match_text.to_string(),
-                    );
+                    };
+
+                    schematic
+                        .symbol_at
+                        .insert(symbol.at, schematic.symbols.len());
+                    schematic.symbols.push(symbol);
                 }
             }
 
-            return Schematic {
-                numbers: numbers,
-                symbols: symbols,
-            };
+            schematic
+        }
+
+        /// Ids of the numbers touching a symbol, each once, in reading order.
+        pub fn numbers_adjacent_to_symbol(&self, symbol_id: usize) -> Vec<usize> {
+            let mut ids: Vec<usize> = self.symbols[symbol_id]
+                .border()
+                .filter_map(|cell| self.number_at.get(&cell).copied())
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+
+            ids
+        }
+
+        /// Ids of the symbols touching a number, in reading order.
+        pub fn symbols_adjacent_to_number(&self, number_id: usize) -> Vec<usize> {
+            let mut ids: Vec<usize> = self.numbers[number_id]
+                .border()
+                .filter_map(|cell| self.symbol_at.get(&cell).copied())
+                .collect();
+            ids.sort_unstable();
+
+            ids
         }
 
         pub fn sum_part_numbers(&self) -> i32 {
-            let mut sum = 0;
-            for number in &self.numbers {
-                if Self::is_part_number(number, &self.symbols) {
-                    sum += number.1;
-                }
-            }
+            (0..self.numbers.len())
+                .filter(|id| !self.symbols_adjacent_to_number(*id).is_empty())
+                .map(|id| self.numbers[id].value)
+                .sum()
+        }
+
+        /// The product of the two numbers touching each of the given symbol that touches
+        /// exactly two numbers.
+        pub fn gear_ratios(&self, symbol: &str) -> Vec<i32> {
+            (0..self.symbols.len())
+                .filter(|id| self.symbols[*id].symbol == symbol)
+                .map(|id| self.numbers_adjacent_to_symbol(id))
+                .filter(|ids| ids.len() == 2)
+                .map(|ids| self.numbers[ids[0]].value * self.numbers[ids[1]].value)
+                .collect()
+        }
+
+        pub fn sum_part_numbers_2(&self) -> i32 {
+            self.gear_ratios("*").iter().sum()
+        }
+
+        pub fn number_reports(&self) -> Vec<NumberReport> {
+            (0..self.numbers.len())
+                .map(|id| NumberReport {
+                    number: self.numbers[id].clone(),
+                    symbols: self
+                        .symbols_adjacent_to_number(id)
+                        .into_iter()
+                        .map(|s| self.symbols[s].clone())
+                        .collect(),
+                })
+                .collect()
+        }
 
-            return sum;
+        pub fn symbol_reports(&self) -> Vec<SymbolReport> {
+            (0..self.symbols.len())
+                .map(|id| SymbolReport {
+                    symbol: self.symbols[id].clone(),
+                    numbers: self
+                        .numbers_adjacent_to_symbol(id)
+                        .into_iter()
+                        .map(|n| self.numbers[n].clone())
+                        .collect(),
+                })
+                .collect()
         }
 
-        fn is_part_number(
-            number: (&Coordinate, &i32),
-            symbols: &HashMap<Coordinate, String>,
-        ) -> bool {
-            let left_x = number.0.x;
-            let right_x = number.0.x + get_number_length(number.1) - 1;
-            let y = number.0.y;
+        pub fn unattached_numbers(&self) -> Vec<Number> {
+            self.number_reports()
+                .into_iter()
+                .filter(|r| r.symbols.is_empty())
+                .map(|r| r.number)
+                .collect()
+        }
 
-            // check above, below, including diagonals
-            for iy in [y - 1, y + 1] {
-                for ix in left_x - 1..=right_x + 1 {
-                    let adjacent = Coordinate { x: ix, y: iy };
+        pub fn symbols_touching_at_least(&self, count: usize) -> Vec<SymbolReport> {
+            self.symbol_reports()
+                .into_iter()
+                .filter(|r| r.numbers.len() >= count)
+                .collect()
+        }
 
-                    if symbols.contains_key(&adjacent) {
-                        return true;
-                    }
+        /// Totals for each kind of symbol, in symbol order. A number touching two symbols
+        /// counts towards both.
+        pub fn totals_by_symbol(&self) -> Vec<SymbolTotal> {
+            let mut totals = BTreeMap::<String, SymbolTotal>::new();
+            for report in self.symbol_reports() {
+                let total = totals
+                    .entry(report.symbol.symbol.clone())
+                    .or_insert_with(|| SymbolTotal {
+                        symbol: report.symbol.symbol.clone(),
+                        count: 0,
+                        part_number_sum: 0,
+                        gear_ratio_sum: 0,
+                    });
+
+                total.count += 1;
+                total.part_number_sum += report.numbers.iter().map(|n| n.value).sum::<i32>();
+                if let [left, right] = report.numbers.as_slice() {
+                    total.gear_ratio_sum += left.value * right.value;
                 }
             }
 
-            // check left and right
-            for ix in [left_x - 1, right_x + 1] {
-                let adjacent = Coordinate { x: ix, y: y };
+            totals.into_values().collect()
+        }
 
-                if symbols.contains_key(&adjacent) {
-                    return true;
-                }
+        pub fn report(&self) -> Report {
+            Report {
+                numbers: self.number_reports(),
+                symbols: self.symbol_reports(),
+                totals: self.totals_by_symbol(),
             }
-
-            return false;
         }
 
-        pub fn sum_part_numbers_2(&self) -> i32 {
-            let symbol_shapes: Vec<(Shape, &str)> = self
-                .symbols
-                .iter()
-                .map(|s| (build_hollow_shape_around_point(s.0), s.1 as &str))
+        /// Part numbers in green, numbers touching no symbol in red, symbols in yellow unless
+        /// they touch three or more numbers, then magenta.
+        pub fn scene(&self) -> Scene {
+            let cells = |numbers: Vec<Number>| -> Vec<(usize, usize)> {
+                numbers
+                    .iter()
+                    .flat_map(|n| n.cells())
+                    .map(|c| (c.x as usize, c.y as usize))
+                    .collect()
+            };
+            let symbol_cells = |symbols: &[Symbol]| -> Vec<(usize, usize)> {
+                symbols
+                    .iter()
+                    .map(|s| (s.at.x as usize, s.at.y as usize))
+                    .collect()
+            };
+
+            let crowded: Vec<Symbol> = self
+                .symbols_touching_at_least(3)
+                .into_iter()
+                .map(|r| r.symbol)
                 .collect();
 
-            let number_shapes: Vec<(Shape, &i32)> = self
+            Scene::new(self.grid.clone())
+                .with_layer(Layer::new(
+                    "part numbers",
+                    Colour::Green,
+                    cells(self.numbers.clone()),
+                ))
+                .with_layer(Layer::new(
+                    "unattached",
+                    Colour::Red,
+                    cells(self.unattached_numbers()),
+                ))
+                .with_layer(Layer::new(
+                    "symbols",
+                    Colour::Yellow,
+                    symbol_cells(&self.symbols),
+                ))
+                .with_layer(Layer::new(
+                    "crowded symbols",
+                    Colour::Magenta,
+                    symbol_cells(&crowded),
+                ))
+        }
+    }
+
+    /// A number and the symbols touching it.
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct NumberReport {
+        pub number: Number,
+        pub symbols: Vec<Symbol>,
+    }
+
+    /// A symbol and the numbers touching it.
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct SymbolReport {
+        pub symbol: Symbol,
+        pub numbers: Vec<Number>,
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct SymbolTotal {
+        pub symbol: String,
+        pub count: usize,
+        /// The sum of the numbers touching symbols of this kind.
+        pub part_number_sum: i32,
+        /// The sum of the products for symbols of this kind touching exactly two numbers.
+        pub gear_ratio_sum: i32,
+    }
+
+    pub struct Report {
+        pub numbers: Vec<NumberReport>,
+        pub symbols: Vec<SymbolReport>,
+        pub totals: Vec<SymbolTotal>,
+    }
+
+    impl Report {
+        pub fn to_json(&self) -> String {
+            let numbers: Vec<String> = self
                 .numbers
                 .iter()
-                .map(|s| {
-                    (
-                        build_shape_on_line(s.0.x, s.0.x + get_number_length(s.1) - 1, s.0.y),
-                        s.1,
+                .map(|r| {
+                    let symbols: Vec<String> = r.symbols.iter().map(symbol_json).collect();
+                    format!(
+                        "{{{},\"symbols\":[{}]}}",
+                        number_fields(&r.number),
+                        symbols.join(",")
                     )
                 })
                 .collect();
 
-            const IS_PART_2: bool = true;
-            let mut total_sum = 0;
-            for symbol_shape in symbol_shapes {
-                if IS_PART_2 && symbol_shape.1 != "*" {
-                    continue;
-                }
-                let mut symbol_sum = 0;
-                let mut how_many_matches = 0;
-                'number: for number_shape in number_shapes.iter() {
-                    for coord in symbol_shape.0.area.iter() {
-                        if number_shape.0.area.contains(coord) {
-                            if IS_PART_2 && symbol_sum != 0 {
-                                symbol_sum *= number_shape.1;
-                                how_many_matches += 1;
-                                continue 'number;
-                            } else {
-                                symbol_sum += number_shape.1;
-                                how_many_matches += 1;
-                                continue 'number;
-                            }
-                        }
-                    }
-                }
+            let symbols: Vec<String> = self
+                .symbols
+                .iter()
+                .map(|r| {
+                    let numbers: Vec<String> = r
+                        .numbers
+                        .iter()
+                        .map(|n| format!("{{{}}}", number_fields(n)))
+                        .collect();
+                    format!(
+                        "{{{},\"numbers\":[{}]}}",
+                        symbol_fields(&r.symbol),
+                        numbers.join(",")
+                    )
+                })
+                .collect();
 
-                // todo shit code
-                if how_many_matches < 2 {
-                    symbol_sum = 0;
-                }
-                total_sum += symbol_sum;
-            }
+            let totals: Vec<String> = self
+                .totals
+                .iter()
+                .map(|t| {
+                    format!(
+                        "{{\"symbol\":\"{}\",\"count\":{},\"part_number_sum\":{},\"gear_ratio_sum\":{}}}",
+                        escape_json(&t.symbol),
+                        t.count,
+                        t.part_number_sum,
+                        t.gear_ratio_sum
+                    )
+                })
+                .collect();
 
-            return total_sum;
+            format!(
+                "{{\"numbers\":[{}],\"symbols\":[{}],\"totals\":[{}]}}",
+                numbers.join(","),
+                symbols.join(","),
+                totals.join(",")
+            )
         }
     }
 
-    struct Shape {
-        area: HashSet<Coordinate>,
+    fn coordinate_json(c: &Coordinate) -> String {
+        format!("{{\"x\":{},\"y\":{}}}", c.x, c.y)
     }
 
-    fn build_hollow_shape_around_point(point: &Coordinate) -> Shape {
-        let mut area = HashSet::<Coordinate>::new();
-        for iy in [point.y - 1, point.y + 1] {
-            for ix in point.x - 1..=point.x + 1 {
-                let adjacent = Coordinate { x: ix, y: iy };
-                area.insert(adjacent);
-            }
-        }
-
-        for ix in [point.x - 1, point.x + 1] {
-            let adjacent = Coordinate { x: ix, y: point.y };
-            area.insert(adjacent);
-        }
-
-        return Shape { area };
+    fn number_fields(n: &Number) -> String {
+        format!(
+            "\"value\":{},\"start\":{},\"length\":{}",
+            n.value,
+            coordinate_json(&n.start),
+            n.length
+        )
     }
 
-    fn build_shape_on_line(left_x: i32, right_x: i32, y: i32) -> Shape {
-        let mut area = HashSet::<Coordinate>::new();
-        for ix in left_x..=right_x {
-            let adjacent = Coordinate { x: ix, y: y };
-            area.insert(adjacent);
-        }
+    fn symbol_fields(s: &Symbol) -> String {
+        format!(
+            "\"symbol\":\"{}\",\"at\":{}",
+            escape_json(&s.symbol),
+            coordinate_json(&s.at)
+        )
+    }
 
-        return Shape { area };
+    fn symbol_json(s: &Symbol) -> String {
+        format!("{{{}}}", symbol_fields(s))
     }
 
-    fn get_number_length(n: &i32) -> i32 {
-        return n.to_string().chars().count() as i32;
+    fn escape_json(text: &str) -> String {
+        text.chars()
+            .map(|c| match c {
+                '"' => String::from("\\\""),
+                '\\' => String::from("\\\\"),
+                c if c.is_control() => format!("\\u{:04x}", c as u32),
+                c => c.to_string(),
+            })
+            .collect()
     }
 }
 
@@ -220,7 +476,113 @@ mod tests {
         let schematic = Schematic::deserialize(&lines);
         let sum = schematic.sum_part_numbers_2();
 
-        //assert_eq!(sum, 4361);
         assert_eq!(sum, 467835);
     }
+
+    #[test]
+    fn can_find_gear_ratio_with_zero_part_number() {
+        let lines = vec![String::from("0*7..."), String::from("..#8..")];
+
+        let schematic = Schematic::deserialize(&lines);
+
+        assert_eq!(schematic.gear_ratios("*"), vec![0]);
+        assert_eq!(schematic.gear_ratios("#"), vec![56]);
+        assert_eq!(schematic.sum_part_numbers_2(), 0);
+    }
+
+    #[test]
+    fn can_find_neighbours_through_index() {
+        let lines = vec![
+            String::from("007.."),
+            String::from("...$."),
+            String::from(".12.."),
+        ];
+
+        let schematic = Schematic::deserialize(&lines);
+
+        assert_eq!(schematic.numbers_adjacent_to_symbol(0), vec![0, 1]);
+        assert_eq!(schematic.symbols_adjacent_to_number(0), vec![0]);
+        assert_eq!(schematic.symbols_adjacent_to_number(1), vec![0]);
+        // 007 is seven, three cells wide
+        assert_eq!(schematic.gear_ratios("$"), vec![84]);
+    }
+
+    #[test]
+    fn can_report_unattached_numbers_and_crowded_symbols() {
+        let lines = vec![
+            String::from("1.2.3"),
+            String::from(".+*.."),
+            String::from("4.5.9"),
+        ];
+
+        let schematic = Schematic::deserialize(&lines);
+
+        assert_eq!(
+            schematic.unattached_numbers(),
+            vec![
+                Number {
+                    value: 3,
+                    start: Coordinate { x: 4, y: 0 },
+                    length: 1,
+                },
+                Number {
+                    value: 9,
+                    start: Coordinate { x: 4, y: 2 },
+                    length: 1,
+                },
+            ]
+        );
+
+        let crowded = schematic.symbols_touching_at_least(3);
+        assert_eq!(crowded.len(), 1);
+        assert_eq!(crowded[0].symbol.symbol, "+");
+        assert_eq!(crowded[0].symbol.at, Coordinate { x: 1, y: 1 });
+        assert_eq!(crowded[0].numbers.len(), 4);
+
+        assert_eq!(
+            schematic.totals_by_symbol(),
+            vec![
+                SymbolTotal {
+                    symbol: String::from("*"),
+                    count: 1,
+                    part_number_sum: 2 + 5,
+                    gear_ratio_sum: 10,
+                },
+                SymbolTotal {
+                    symbol: String::from("+"),
+                    count: 1,
+                    part_number_sum: 1 + 2 + 4 + 5,
+                    gear_ratio_sum: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn can_export_report_as_json() {
+        let lines = vec![String::from("12\"."), String::from("....")];
+
+        let json = Schematic::deserialize(&lines).report().to_json();
+
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"numbers":[{"value":12,"start":{"x":0,"y":0},"length":2,"symbols":[{"symbol":"\"","at":{"x":2,"y":0}}]}],"#,
+                r#""symbols":[{"symbol":"\"","at":{"x":2,"y":0},"numbers":[{"value":12,"start":{"x":0,"y":0},"length":2}]}],"#,
+                r#""totals":[{"symbol":"\"","count":1,"part_number_sum":12,"gear_ratio_sum":0}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn can_render_highlighted_grid() {
+        let lines = vec![String::from("1.2"), String::from("#..")];
+
+        let ansi = Schematic::deserialize(&lines).scene().to_ansi();
+
+        assert_eq!(
+            ansi,
+            "\x1b[32m1\x1b[0m.\x1b[31m2\x1b[0m\n\x1b[33m#\x1b[0m..\n"
+        );
+    }
 }
@@ -1,5 +1,6 @@
-use crate::gambling::parse_scratchcards;
+use crate::gambling::{parse_packed_scratchcard, parse_scratchcard, parse_scratchcards, Overflow};
 use common::file;
+use std::{env, time::Instant};
 
 fn main() {
     let file = String::from("./input.txt");
@@ -7,19 +8,74 @@ fn main() {
 
     let collection = parse_scratchcards(&lines);
 
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("bench") {
+        return bench(args.get(2), &lines);
+    }
+
+    if let Some(rule) = args.get(1) {
+        let overflow = match rule.as_str() {
+            "clamp" => Overflow::Clamp,
+            "error" => Overflow::Error,
+            "extend" => Overflow::Extend,
+            other => panic!("unknown overflow rule '{}'", other),
+        };
+
+        // Explain where every card's copies came from
+        for card in collection.cascade(overflow).unwrap() {
+            let sources: Vec<String> = card
+                .won_from
+                .iter()
+                .map(|(id, copies)| format!("{} from card {}", copies, id))
+                .collect();
+            println!("card {}: {} ({})", card.id, card.copies, sources.join(", "));
+        }
+        return;
+    }
+
     println!("part 1: {}", collection.winnings_part_1().0);
 
-    println!("part 2: {}", collection.winnings_part_2().0);
+    println!("part 2: {}", collection.winnings_part_2().unwrap());
+}
+
+/// Time parsing every card and counting its matches with the regex parser against the byte
+/// parser.
+fn bench(runs: Option<&String>, lines: &[String]) {
+    let runs: u32 = runs.map(|r| r.parse().unwrap()).unwrap_or(100);
+
+    let start = Instant::now();
+    let mut regex_matches = 0;
+    for _ in 0..runs {
+        for line in lines {
+            regex_matches += parse_scratchcard(line).number_of_matching_numbers();
+        }
+    }
+    let regex = start.elapsed();
+
+    let start = Instant::now();
+    let mut packed_matches = 0;
+    for _ in 0..runs {
+        for line in lines {
+            packed_matches += parse_packed_scratchcard(line)
+                .unwrap()
+                .number_of_matching_numbers();
+        }
+    }
+    let packed = start.elapsed();
+
+    assert_eq!(regex_matches, packed_matches);
+    println!(
+        "regex {:?}/run, packed {:?}/run, {:.0}x faster",
+        regex / runs,
+        packed / runs,
+        regex.as_secs_f64() / packed.as_secs_f64()
+    );
 }
 
 mod gambling {
+    use common::core::{Error, Result};
     use regex::Regex;
-    use std::{
-        arch::asm,
-        collections::{HashMap, HashSet},
-        iter::Sum,
-        ops::Add,
-    };
+    use std::{collections::HashSet, iter::Sum, ops::Add};
 
     pub struct ScratchCardCollection {
         scratch_cards: Vec<ScratchCard>,
@@ -30,32 +86,120 @@ mod gambling {
             self.scratch_cards.iter().map(|s| s.points()).sum()
         }
 
-        pub fn winnings_part_2(&self) -> Point {
-            const STARTING_CARD_COUNT: i32 = 1;
-            let mut all_won_cards: HashMap<i32, i32> = self
+        pub fn winnings_part_2(&self) -> Result<i64> {
+            let cascade = self.cascade(Overflow::Clamp)?;
+
+            cascade.iter().try_fold(0i64, |total, card| {
+                total
+                    .checked_add(card.copies)
+                    .ok_or_else(|| Error::new("more cards are won than can be counted"))
+            })
+        }
+
+        /// Play the cards in id order: a card with `n` matches wins a copy of each of the next
+        /// `n` ids for every copy of it held. Cards that don't exist are handled by `overflow`.
+        pub fn cascade(&self, overflow: Overflow) -> Result<Vec<CardCopies>> {
+            let mut cards: Vec<(i32, u32)> = self
                 .scratch_cards
                 .iter()
-                .map(|s| (s.id, STARTING_CARD_COUNT))
+                .map(|s| (s.id(), s.number_of_matching_numbers()))
                 .collect();
+            cards.sort_unstable();
 
-            for card in self.scratch_cards.iter() {
-                let matching = card.number_of_matching_numbers() as i32;
-                let card_count = all_won_cards.get(&card.id).unwrap().clone();
-                let won_card_ids = (card.id + 1)..=(card.id + matching);
+            if let Some(pair) = cards.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(Error::new(&format!("card {} appears twice", pair[0].0)));
+            }
 
-                for card_id in won_card_ids {
-                    all_won_cards
-                        .entry(card_id)
-                        .and_modify(|v| *v += card_count);
+            if overflow == Overflow::Extend {
+                let mut missing: Vec<i32> = vec![];
+                for (id, matching) in cards.iter() {
+                    missing.extend(
+                        won_ids(*id, *matching, overflow)?
+                            .filter(|won| cards.binary_search_by_key(won, |(id, _)| *id).is_err()),
+                    );
                 }
+                missing.sort_unstable();
+                missing.dedup();
+
+                cards.extend(missing.into_iter().map(|id| (id, 0)));
+                cards.sort_unstable();
             }
 
-            let number_of_won_cards: i32 = all_won_cards.iter().map(|(k, v)| v.clone()).sum();
+            let mut copies: Vec<CardCopies> = cards
+                .iter()
+                .map(|(id, _)| CardCopies {
+                    id: *id,
+                    copies: 1,
+                    won_from: vec![],
+                })
+                .collect();
+
+            for (i, (id, matching)) in cards.iter().enumerate() {
+                let card_count = copies[i].copies;
+
+                for won_id in won_ids(*id, *matching, overflow)? {
+                    match cards.binary_search_by_key(&won_id, |(id, _)| *id) {
+                        Ok(won) => {
+                            copies[won].copies =
+                                copies[won].copies.checked_add(card_count).ok_or_else(|| {
+                                    Error::new(&format!(
+                                        "card {} is won more times than can be counted",
+                                        won_id
+                                    ))
+                                })?;
+                            copies[won].won_from.push((*id, card_count));
+                        }
+                        Err(_) if overflow == Overflow::Clamp => (),
+                        Err(_) => {
+                            return Err(Error::new(&format!(
+                                "card {} wins card {}, which doesn't exist",
+                                id, won_id
+                            )))
+                        }
+                    }
+                }
+            }
 
-            return Point(number_of_won_cards);
+            Ok(copies)
         }
     }
 
+    /// The ids a card with `matching` matches wins. Ids past `i32::MAX` can't be in the
+    /// collection, so they are dropped when clamping and an error otherwise.
+    fn won_ids(id: i32, matching: u32, overflow: Overflow) -> Result<impl Iterator<Item = i32>> {
+        let last = match i32::try_from(matching).ok().and_then(|m| id.checked_add(m)) {
+            Some(last) => last,
+            None if overflow == Overflow::Clamp => i32::MAX,
+            None => {
+                return Err(Error::new(&format!(
+                    "card {} wins cards past the largest id",
+                    id
+                )))
+            }
+        };
+
+        Ok((id..=last).skip(1))
+    }
+
+    /// What to do when a card wins copies of cards that aren't in the collection.
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub enum Overflow {
+        /// Ignore them, as the puzzle says.
+        Clamp,
+        Error,
+        /// Add blank cards with those ids, which win nothing themselves.
+        Extend,
+    }
+
+    /// How many of a card are held at the end, and which cards won the extra copies.
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct CardCopies {
+        pub id: i32,
+        pub copies: i64,
+        /// `(card id, copies won)` for each earlier card that won this one.
+        pub won_from: Vec<(i32, i64)>,
+    }
+
     pub struct ScratchCard {
         id: i32,
         winning_numbers: Vec<i32>,
@@ -68,7 +212,7 @@ mod gambling {
         }
 
         pub fn points(&self) -> Point {
-            let matching = self.number_of_matching_numbers() as u32;
+            let matching = self.number_of_matching_numbers();
 
             match matching == 0 {
                 true => Point(0),
@@ -106,7 +250,7 @@ mod gambling {
             Point(self.0 + other.0)
         }
     }
-    impl<'p> Add for &'p Point {
+    impl Add for &Point {
         type Output = Point;
         fn add(self, other: &Point) -> Point {
             Point(self.0 + other.0)
@@ -123,16 +267,15 @@ mod gambling {
         let mut parts = line.split(":");
         let id = parse_id(parts.next().unwrap());
         let mut number_parts = parts.next().unwrap().split('|');
-        ScratchCard {
+        ScratchCard::new(
             id,
-            winning_numbers: parse_numbers(number_parts.next().unwrap()),
-            numbers: parse_numbers(number_parts.next().unwrap()),
-        }
+            parse_numbers(number_parts.next().unwrap()),
+            parse_numbers(number_parts.next().unwrap()),
+        )
     }
 
     fn parse_numbers(numbers_str: &str) -> Vec<i32> {
         numbers_str
-            .trim()
             .split_whitespace()
             .map(|num| num.parse().unwrap())
             .collect()
@@ -140,26 +283,92 @@ mod gambling {
 
     fn parse_id(card_name_str: &str) -> i32 {
         let num_regex = Regex::new(r"\d+").unwrap();
-        let match_ = num_regex.find_iter(card_name_str).nth(0).unwrap();
+        let match_ = num_regex.find_iter(card_name_str).next().unwrap();
         let digits = &card_name_str[match_.start()..match_.end()];
         digits.parse::<i32>().unwrap()
     }
 
-    fn parse_id_2(card_name_str: &str) -> i32 {
-        let bytes = card_name_str.as_bytes();
-        let mut id_buf = [0_u8; 12];
-        unsafe {
-            asm!("nop");
+    /// A card with its numbers as bits, so matching is a single AND.
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub struct PackedCard {
+        pub id: i32,
+        pub winning_numbers: u128,
+        pub numbers: u128,
+    }
+
+    impl PackedCard {
+        pub fn number_of_matching_numbers(&self) -> u32 {
+            (self.winning_numbers & self.numbers).count_ones()
+        }
+    }
+
+    /// Parse a card line a byte at a time without allocating. Numbers must be below 128.
+    pub fn parse_packed_scratchcard(line: &str) -> Result<PackedCard> {
+        let (name, numbers) = line
+            .split_once(':')
+            .ok_or_else(|| Error::new("missing ':' after the card name"))?;
+        let (winning_numbers, numbers) = numbers
+            .split_once('|')
+            .ok_or_else(|| Error::new("missing '|' between the numbers"))?;
+
+        Ok(PackedCard {
+            id: parse_id_2(name)?,
+            winning_numbers: parse_bitmask(winning_numbers)?,
+            numbers: parse_bitmask(numbers)?,
+        })
+    }
+
+    fn parse_bitmask(numbers_str: &str) -> Result<u128> {
+        let mut mask = 0_u128;
+        let mut number: Option<u32> = None;
+
+        // A trailing space flushes the last number
+        for byte in numbers_str.bytes().chain([b' ']) {
+            match byte {
+                b'0'..=b'9' => {
+                    let n = number.unwrap_or(0) * 10 + (byte - b'0') as u32;
+                    if n >= u128::BITS {
+                        return Err(Error::new(&format!("{}... doesn't fit in the bitmask", n)));
+                    }
+                    number = Some(n);
+                }
+                b' ' => {
+                    if let Some(n) = number.take() {
+                        mask |= 1 << n;
+                    }
+                }
+                _ => return Err(Error::new(&format!("unexpected '{}'", byte as char))),
+            }
+        }
+
+        Ok(mask)
+    }
+
+    fn parse_id_2(card_name_str: &str) -> Result<i32> {
+        let digits = card_name_str
+            .strip_prefix("Card")
+            .ok_or_else(|| Error::new("card name doesn't start with 'Card'"))?
+            .trim_start_matches(' ')
+            .as_bytes();
+
+        if digits.is_empty() || digits.len() > 9 {
+            return Err(Error::new("card id should have 1 to 9 digits"));
         }
 
-        todo!();
+        digits.iter().try_fold(0, |id, byte| match byte {
+            b'0'..=b'9' => Ok(id * 10 + (byte - b'0') as i32),
+            _ => Err(Error::new(&format!(
+                "unexpected '{}' in card id",
+                *byte as char
+            ))),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::gambling::*;
-    use common::file;
+    use common::{file, random::Random};
 
     #[test]
     fn can_find_sum_of_winnings_for_example_file() {
@@ -180,16 +389,16 @@ mod tests {
 
         let collection = parse_scratchcards(&lines);
 
-        let winnings = collection.winnings_part_2();
+        let winnings = collection.winnings_part_2().unwrap();
 
-        assert_eq!(winnings.0, 30);
+        assert_eq!(winnings, 30);
     }
 
     #[test]
     fn can_parse_scratch_card_line() {
         let line = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
 
-        let scratch_card = parse_scratchcard(&line);
+        let scratch_card = parse_scratchcard(line);
 
         assert_eq!(scratch_card.id(), 1);
         assert_eq!(scratch_card.points().0, 8);
@@ -207,23 +416,187 @@ mod tests {
 
         assert_eq!(points.0, 8);
     }
-}
 
-mod stuff {
-    #[derive(Debug)]
-    pub struct RefEquality<'a, T>(pub &'a T);
-    impl<'a, T> std::hash::Hash for RefEquality<'a, T> {
-        fn hash<H>(&self, state: &mut H)
-        where
-            H: std::hash::Hasher,
-        {
-            (self.0 as *const T).hash(state)
-        }
+    #[test]
+    fn can_explain_where_copies_came_from() {
+        let file = String::from("./example.txt");
+        let lines = file::read_lines(&file).unwrap();
+
+        let cascade = parse_scratchcards(&lines).cascade(Overflow::Clamp).unwrap();
+
+        let copies: Vec<i64> = cascade.iter().map(|c| c.copies).collect();
+        assert_eq!(copies, vec![1, 2, 4, 8, 14, 1]);
+        assert_eq!(cascade[3].won_from, vec![(1, 1), (2, 2), (3, 4)]);
+        assert_eq!(cascade[5].won_from, vec![]);
+    }
+
+    #[test]
+    fn can_play_cards_in_any_order() {
+        let lines = [
+            "Card 30: 5 | 6",
+            "Card 10: 1 2 | 1 2",
+            "Card 11: 3 | 3",
+            "Card 12: 4 | 7",
+        ];
+        let collection = parse_scratchcards(&lines.map(String::from));
+
+        let cascade = collection.cascade(Overflow::Clamp).unwrap();
+
+        let copies: Vec<(i32, i64)> = cascade.iter().map(|c| (c.id, c.copies)).collect();
+        assert_eq!(copies, vec![(10, 1), (11, 2), (12, 4), (30, 1)]);
+    }
+
+    #[test]
+    fn can_choose_what_happens_past_the_last_card() {
+        let lines = ["Card 1: 1 | 1", "Card 2: 1 2 | 1 2"];
+        let collection = parse_scratchcards(&lines.map(String::from));
+
+        let clamped = collection.cascade(Overflow::Clamp).unwrap();
+        let extended = collection.cascade(Overflow::Extend).unwrap();
+        let error = collection.cascade(Overflow::Error).unwrap_err();
+
+        assert_eq!(clamped.iter().map(|c| c.copies).sum::<i64>(), 3);
+        assert_eq!(
+            extended,
+            vec![
+                CardCopies {
+                    id: 1,
+                    copies: 1,
+                    won_from: vec![],
+                },
+                CardCopies {
+                    id: 2,
+                    copies: 2,
+                    won_from: vec![(1, 1)],
+                },
+                CardCopies {
+                    id: 3,
+                    copies: 3,
+                    won_from: vec![(2, 2)],
+                },
+                CardCopies {
+                    id: 4,
+                    copies: 3,
+                    won_from: vec![(2, 2)],
+                },
+            ]
+        );
+        assert_eq!(
+            error.get_reason(),
+            "card 2 wins card 3, which doesn't exist"
+        );
+    }
+
+    #[test]
+    fn can_win_cards_near_the_largest_id() {
+        let max = i32::MAX;
+        let lines = [
+            format!("Card {}: 1 2 | 1 2", max - 1),
+            format!("Card {}: 1 | 1", max),
+        ];
+        let collection = parse_scratchcards(&lines);
+
+        let clamped = collection.cascade(Overflow::Clamp).unwrap();
+        let error = collection.cascade(Overflow::Error).unwrap_err();
+        let extended = collection.cascade(Overflow::Extend).unwrap_err();
+
+        assert_eq!(clamped.iter().map(|c| c.copies).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(collection.winnings_part_2().unwrap(), 3);
+        assert_eq!(
+            error.get_reason(),
+            &format!("card {} wins cards past the largest id", max - 1)
+        );
+        assert_eq!(extended.get_reason(), error.get_reason());
+    }
+
+    #[test]
+    fn can_report_too_many_copies() {
+        // Every card wins all the cards after it, so card n is held 2^(n-1) times
+        let lines: Vec<String> = (1..=64)
+            .map(|id| {
+                let numbers: Vec<String> = (0..=64 - id).map(|n| n.to_string()).collect();
+                format!(
+                    "Card {}: {} | {} 99",
+                    id,
+                    numbers[1..].join(" "),
+                    numbers.join(" ")
+                )
+            })
+            .collect();
+        let collection = parse_scratchcards(&lines);
+
+        assert_eq!(
+            collection.winnings_part_2().unwrap_err().get_reason(),
+            "card 64 is won more times than can be counted"
+        );
+    }
+
+    #[test]
+    fn refuses_to_count_winnings_with_duplicate_cards() {
+        let lines = ["Card 1: 1 | 1", "Card 1: 2 | 3"];
+        let collection = parse_scratchcards(&lines.map(String::from));
+
+        assert_eq!(
+            collection.winnings_part_2().unwrap_err().get_reason(),
+            "card 1 appears twice"
+        );
+    }
+
+    fn assert_parsers_agree(line: &str) {
+        let card = parse_scratchcard(line);
+        let packed = parse_packed_scratchcard(line).unwrap();
+
+        assert_eq!(packed.id, card.id(), "{}", line);
+        assert_eq!(
+            packed.number_of_matching_numbers(),
+            card.number_of_matching_numbers(),
+            "{}",
+            line
+        );
     }
-    impl<'a, 'b, T> PartialEq<RefEquality<'b, T>> for RefEquality<'a, T> {
-        fn eq(&self, other: &RefEquality<'b, T>) -> bool {
-            self.0 as *const T == other.0 as *const T
+
+    #[test]
+    fn packed_parser_agrees_with_regex_parser() {
+        for filename in ["./example.txt", "./input.txt"] {
+            for line in file::read_lines(filename).unwrap() {
+                assert_parsers_agree(&line);
+            }
+        }
+
+        // Random cards, with repeated numbers and uneven spacing
+        let mut random = Random::new(2023);
+        let mut next = |below: u64| random.next(below);
+        for id in 1..500 {
+            let mut numbers = |count| -> String {
+                (0..count)
+                    .map(|_| format!("{:>1$}", next(100), 1 + next(3) as usize))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            };
+            let line = format!("Card {:>4}: {} | {}", id, numbers(10), numbers(25));
+            assert_parsers_agree(&line);
         }
     }
-    impl<'a, T> Eq for RefEquality<'a, T> {}
+
+    #[test]
+    fn packed_parser_rejects_bad_lines() {
+        let error = |line: &str| {
+            parse_packed_scratchcard(line)
+                .unwrap_err()
+                .get_reason()
+                .clone()
+        };
+
+        assert_eq!(error("Card 1: 1 2 3"), "missing '|' between the numbers");
+        assert_eq!(
+            error("Crab 1: 1 | 2"),
+            "card name doesn't start with 'Card'"
+        );
+        assert_eq!(error("Card x: 1 | 2"), "unexpected 'x' in card id");
+        assert_eq!(
+            error("Card 1: 1 | 128"),
+            "128... doesn't fit in the bitmask"
+        );
+        assert_eq!(error("Card 1: 1, 2 | 2"), "unexpected ','");
+    }
 }
@@ -1,6 +1,8 @@
+use std::env;
+
 use common::file;
 
-use crate::boats::{number_of_better_solutions, parse, part_1, GameResult};
+use crate::boats::{number_of_better_solutions, parse, parse_joined, part_1, Charge, Model};
 
 fn main() {
     let file = String::from("./input.txt");
@@ -8,25 +10,67 @@ fn main() {
 
     let results = parse(&lines);
 
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 {
+        return analyse_with_model(&args[1..], &lines);
+    }
+
     println!("part 1: {}", part_1(&results));
 
     println!(
         "part 2: {}",
-        number_of_better_solutions(&GameResult {
-            time: 48876981,
-            distance: 255128811171623,
-        })
+        number_of_better_solutions(&parse_joined(&lines))
     );
 }
 
+/// Race every input race with `standard` or `<linear|quadratic|capped> <rate> [max speed]`,
+/// optionally with `drag=N`.
+fn analyse_with_model(args: &[String], lines: &[String]) {
+    let number = |i: usize| args[i].parse::<i64>().unwrap();
+    let charge = match args[0].as_str() {
+        "standard" => Model::standard().charge,
+        "linear" => Charge::Linear { rate: number(1) },
+        "quadratic" => Charge::Quadratic { rate: number(1) },
+        "capped" => Charge::Capped {
+            rate: number(1),
+            max: number(2),
+        },
+        other => panic!("unknown charge '{}'", other),
+    };
+    let drag = args
+        .iter()
+        .find_map(|a| a.strip_prefix("drag="))
+        .map_or(0, |d| d.parse().unwrap());
+    let model = Model { charge, drag };
+
+    let mut races = parse(lines);
+    races.push(parse_joined(lines));
+    for race in races.iter() {
+        match model.analyse(race) {
+            Ok(analysis) => println!(
+                "time {} record {}: {} winning holds, best is holding {} to go {}",
+                race.time,
+                race.distance,
+                analysis.winning_holds,
+                analysis.optimal_hold,
+                analysis.best_distance
+            ),
+            Err(e) => println!(
+                "time {} record {}: {}",
+                race.time,
+                race.distance,
+                e.get_reason()
+            ),
+        }
+    }
+}
+
 mod boats {
+    use common::core::{Error, Result};
     use common::parsing::parse_numbers;
 
     pub fn part_1(results: &[GameResult]) -> i64 {
-        results
-            .iter()
-            .map(|r| number_of_better_solutions(&r))
-            .fold(1, |l, r| l * r)
+        results.iter().map(number_of_better_solutions).product()
     }
 
     pub struct GameResult {
@@ -43,39 +87,201 @@ mod boats {
            The line is either intersects with the first line
             0 times (ie impossible), 1 time (ie optimal) or 2 times (ie sub-optimal)
            In the case of 2 times, answers with higher distances are found between those points.
+
+           Rearranged, a better hold time x is one where "0 > x^2 - time*x + distance".
         */
+        let time = result.time as i128;
+
+        // Coefficients from i64 inputs are far too small to overflow i128
+        count_below_zero([1, -time, result.distance as i128], 0, time).unwrap() as i64
+    }
 
-        let inputs = infer_inputs_from_distance(result);
+    /// Count the integers `x` in `from..=to` where `a*x^2 + b*x + c < 0`, for `a > 0`.
+    ///
+    /// Works in integers throughout: the roots are only estimated with an integer square root,
+    /// then the first and last solutions are settled by evaluating the polynomial exactly.
+    /// `None` if the coefficients are too big for that to fit in an i128.
+    pub fn count_below_zero([a, b, c]: [i128; 3], from: i128, to: i128) -> Option<i128> {
+        let below_zero = |x: i128| -> Option<bool> {
+            let value = a.checked_mul(x)?.checked_mul(x)?;
+            Some(value.checked_add(b.checked_mul(x)?)?.checked_add(c)? < 0)
+        };
+        let first_below_zero = |candidates: [i128; 4]| -> Option<Option<i128>> {
+            for x in candidates {
+                if below_zero(x)? {
+                    return Some(Some(x));
+                }
+            }
+            Some(None)
+        };
 
-        let int_min_or_below = f64::floor(inputs[0]) as i64;
-        let int_max_or_above = f64::ceil(inputs[1]) as i64;
-        let better_integer_solutions = (int_min_or_below + 1)..int_max_or_above;
+        let discriminant = b
+            .checked_mul(b)?
+            .checked_sub(a.checked_mul(c)?.checked_mul(4)?)?;
+        if discriminant <= 0 {
+            return Some(0);
+        }
+        let root = discriminant.isqrt();
+        let (minus_b, two_a) = (b.checked_neg()?, a.checked_mul(2)?);
+        let around = |x: i128| Some([x.checked_sub(1)?, x, x.checked_add(1)?, x.checked_add(2)?]);
+
+        // Each estimate is within one of the true root, so the edge solution is close by
+        let low = minus_b.checked_sub(root)?.div_euclid(two_a);
+        let first = match first_below_zero(around(low)?)? {
+            Some(first) => first,
+            None => return Some(0),
+        };
 
-        better_integer_solutions.into_iter().count() as i64
+        let high = minus_b.checked_add(root)?.div_euclid(two_a);
+        let mut window = around(high)?;
+        window.reverse();
+        let last = first_below_zero(window)?.unwrap();
+
+        Some(
+            last.min(to)
+                .checked_sub(first.max(from))?
+                .checked_add(1)?
+                .max(0),
+        )
     }
 
-    fn infer_inputs_from_distance(result: &GameResult) -> [f64; 2] {
-        /*
-           To find intersection between
-            "distance = CONST_VALUE" and "Distance = Speed * (Time - Speed)"
-           use quadratic formula
-            x = (-b +or- sqrt(b^2 -4ac) ) / 2a
-           When x is speed and "0 = x^2 - time*x + distance"
-            Then a=-1, b=-time, c=distance
-        */
-        const A: i64 = 1;
-        let b = -result.time;
-        let c = result.distance;
+    /// How fast the boat goes after the button is held for `hold` ms.
+    #[derive(Clone, Copy, Debug)]
+    pub enum Charge {
+        /// Speed is `rate * hold`, the puzzle's model when `rate` is one.
+        Linear { rate: i64 },
+        /// The boat speeds up faster the longer it is held: speed is `rate * hold^2`.
+        Quadratic { rate: i64 },
+        /// Linear until the boat reaches `max` speed.
+        Capped { rate: i64, max: i64 },
+    }
 
-        let sqrt_part = f64::sqrt(((b * b) - 4 * A * c) as f64);
+    impl Charge {
+        fn speed(&self, hold: i128) -> Option<i128> {
+            match *self {
+                Charge::Linear { rate } => hold.checked_mul(rate as i128),
+                Charge::Quadratic { rate } => hold.checked_mul(hold)?.checked_mul(rate as i128),
+                Charge::Capped { rate, max } => {
+                    Some(hold.checked_mul(rate as i128)?.min(max as i128))
+                }
+            }
+        }
+    }
 
-        let max_solution = (-b as f64 + sqrt_part) / (2 * A) as f64;
-        let min_solution = (-b as f64 - sqrt_part) / (2 * A) as f64;
+    /// A boat: how it charges, and how much speed it loses to `drag` once moving (never going
+    /// backwards).
+    #[derive(Clone, Copy, Debug)]
+    pub struct Model {
+        pub charge: Charge,
+        pub drag: i64,
+    }
 
-        return [min_solution, max_solution];
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Analysis {
+        pub winning_holds: i64,
+        /// The shortest hold that goes furthest.
+        pub optimal_hold: i64,
+        pub best_distance: i128,
     }
 
-    pub fn parse(lines: &Vec<String>) -> Vec<GameResult> {
+    impl Model {
+        pub fn standard() -> Self {
+            Model {
+                charge: Charge::Linear { rate: 1 },
+                drag: 0,
+            }
+        }
+
+        pub fn distance(&self, hold: i64, time: i64) -> Option<i128> {
+            let speed = self.charge.speed(hold as i128)? - self.drag as i128;
+
+            speed.max(0).checked_mul((time - hold) as i128)
+        }
+
+        pub fn analyse(&self, race: &GameResult) -> Result<Analysis> {
+            if race.time < 0 || race.distance < 0 {
+                return Err(Error::new("time and record can't be negative"));
+            }
+
+            match self.charge {
+                Charge::Linear { rate } if rate > 0 => self.analyse_linear(rate, race),
+                _ => self.analyse_by_scan(race),
+            }
+        }
+
+        /// Once moving, "Distance = (rate*hold - drag) * (time - hold)" is still a hill, so the
+        /// winning holds lie between its roots and the best is at the top.
+        fn analyse_linear(&self, rate: i64, race: &GameResult) -> Result<Analysis> {
+            let (rate, drag) = (rate as i128, self.drag as i128);
+            let (time, record) = (race.time as i128, race.distance as i128);
+            let overflow = || {
+                Error::new(&format!(
+                    "distance overflows with a charge rate of {}",
+                    rate
+                ))
+            };
+
+            // The first hold that overcomes drag
+            let moving = (drag.max(0) + rate - 1) / rate;
+
+            let peak = rate
+                .checked_mul(time)
+                .and_then(|t| t.checked_add(drag))
+                .ok_or_else(overflow)?;
+            let floor = drag
+                .checked_mul(time)
+                .and_then(|t| t.checked_add(record))
+                .ok_or_else(overflow)?;
+            let winning_holds =
+                count_below_zero([rate, -peak, floor], moving, time).ok_or_else(overflow)?;
+
+            let distance = |hold: i128| -> Result<i128> {
+                rate.checked_mul(hold)
+                    .map(|speed| (speed - drag).max(0))
+                    .and_then(|speed| speed.checked_mul(time - hold))
+                    .ok_or_else(overflow)
+            };
+            let top = peak.div_euclid(2 * rate);
+            let mut optimal_hold = 0;
+            for hold in [top, top + 1] {
+                if (0..=time).contains(&hold) && distance(hold)? > distance(optimal_hold)? {
+                    optimal_hold = hold;
+                }
+            }
+
+            Ok(Analysis {
+                winning_holds: winning_holds as i64,
+                optimal_hold: optimal_hold as i64,
+                best_distance: distance(optimal_hold)?,
+            })
+        }
+
+        fn analyse_by_scan(&self, race: &GameResult) -> Result<Analysis> {
+            let mut analysis = Analysis {
+                winning_holds: 0,
+                optimal_hold: 0,
+                best_distance: 0,
+            };
+
+            for hold in 0..=race.time {
+                let distance = self.distance(hold, race.time).ok_or_else(|| {
+                    Error::new(&format!("distance overflows when holding for {}", hold))
+                })?;
+
+                if distance > race.distance as i128 {
+                    analysis.winning_holds += 1;
+                }
+                if distance > analysis.best_distance {
+                    analysis.optimal_hold = hold;
+                    analysis.best_distance = distance;
+                }
+            }
+
+            Ok(analysis)
+        }
+    }
+
+    pub fn parse(lines: &[String]) -> Vec<GameResult> {
         let mut line_iter = lines.iter();
 
         let times = parse_line(line_iter.next().unwrap());
@@ -84,12 +290,22 @@ mod boats {
         let mut results = Vec::<GameResult>::new();
         for (t, d) in times.iter().zip(distances.iter()) {
             results.push(GameResult {
-                time: t.clone(),
-                distance: d.clone(),
+                time: *t,
+                distance: *d,
             })
         }
 
-        return results;
+        results
+    }
+
+    /// Part two: the spaces between numbers are bad kerning, so there is one race.
+    pub fn parse_joined(lines: &[String]) -> GameResult {
+        let mut line_iter = lines.iter();
+
+        GameResult {
+            time: parse_joined_line(line_iter.next().unwrap()),
+            distance: parse_joined_line(line_iter.next().unwrap()),
+        }
     }
 
     fn parse_line(line: &str) -> Vec<i64> {
@@ -98,6 +314,19 @@ mod boats {
 
         parse_numbers::<i64>(parts.next().unwrap())
     }
+
+    fn parse_joined_line(line: &str) -> i64 {
+        let mut parts = line.split(":");
+        let _title = parts.next().unwrap();
+
+        parts
+            .next()
+            .unwrap()
+            .split_whitespace()
+            .collect::<String>()
+            .parse::<i64>()
+            .unwrap()
+    }
 }
 
 #[cfg(test)]
@@ -169,4 +398,201 @@ mod tests {
             9
         );
     }
+
+    #[test]
+    fn can_solve_part_2_for_example_file() {
+        let file = String::from("./example.txt");
+        let lines = file::read_lines(&file).unwrap();
+
+        let result = parse_joined(&lines);
+
+        assert_eq!(result.time, 71530);
+        assert_eq!(result.distance, 940200);
+        assert_eq!(number_of_better_solutions(&result), 71503);
+    }
+
+    #[test]
+    fn can_exclude_hold_times_that_only_equal_the_record() {
+        // Holding 4 or 6 travels exactly 24, so only 5 beats it
+        assert_eq!(
+            number_of_better_solutions(&GameResult {
+                time: 10,
+                distance: 24
+            }),
+            1
+        );
+        // Holding 5 only equals the record
+        assert_eq!(
+            number_of_better_solutions(&GameResult {
+                time: 10,
+                distance: 25
+            }),
+            0
+        );
+    }
+
+    #[test]
+    fn can_find_no_solutions() {
+        assert_eq!(
+            number_of_better_solutions(&GameResult {
+                time: 10,
+                distance: 26
+            }),
+            0
+        );
+        assert_eq!(
+            number_of_better_solutions(&GameResult {
+                time: 0,
+                distance: 0
+            }),
+            0
+        );
+    }
+
+    #[test]
+    fn can_count_races_too_big_for_i64_discriminant() {
+        // time^2 overflows i64, and the only better hold is exactly half the time
+        let half = 3_000_000_001i64;
+        let result = GameResult {
+            time: 2 * half,
+            distance: half * half - 1,
+        };
+
+        assert_eq!(number_of_better_solutions(&result), 1);
+        assert_eq!(count_below_zero([1, 0, -4], -10, 10), Some(3));
+        assert_eq!(count_below_zero([1, 0, -4], 0, 10), Some(2));
+        assert_eq!(count_below_zero([1, i128::MAX, 0], 0, 10), None);
+    }
+
+    fn analyse_by_scan(model: &Model, time: i64, record: i64) -> (i64, i128) {
+        let distances: Vec<i128> = (0..=time)
+            .map(|hold| model.distance(hold, time).unwrap())
+            .collect();
+        let best = *distances.iter().max().unwrap();
+
+        (
+            distances.iter().filter(|d| **d > record as i128).count() as i64,
+            best,
+        )
+    }
+
+    #[test]
+    fn can_analyse_linear_models_exactly() {
+        for rate in 1..4 {
+            for drag in 0..12 {
+                let model = Model {
+                    charge: Charge::Linear { rate },
+                    drag,
+                };
+                for (time, record) in [(7, 9), (15, 40), (30, 200), (10, 0), (3, 100)] {
+                    let race = GameResult {
+                        time,
+                        distance: record,
+                    };
+
+                    let analysis = model.analyse(&race).unwrap();
+
+                    let (winning_holds, best_distance) = analyse_by_scan(&model, time, record);
+                    assert_eq!(
+                        analysis.winning_holds, winning_holds,
+                        "{:?} {}",
+                        model, time
+                    );
+                    assert_eq!(
+                        analysis.best_distance, best_distance,
+                        "{:?} {}",
+                        model, time
+                    );
+                    assert_eq!(
+                        model.distance(analysis.optimal_hold, time),
+                        Some(best_distance)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn can_analyse_other_charges_by_scanning() {
+        let race = GameResult {
+            time: 10,
+            distance: 100,
+        };
+
+        let quadratic = Model {
+            charge: Charge::Quadratic { rate: 1 },
+            drag: 0,
+        };
+        // hold^2 * (10 - hold) peaks at 6 and 7 with 144 and 147
+        assert_eq!(
+            quadratic.analyse(&race).unwrap(),
+            Analysis {
+                winning_holds: 4,
+                optimal_hold: 7,
+                best_distance: 147,
+            }
+        );
+
+        let capped = Model {
+            charge: Charge::Capped { rate: 5, max: 20 },
+            drag: 2,
+        };
+        // Top speed of 18 from holding 4 gives 108
+        assert_eq!(
+            capped.analyse(&race).unwrap(),
+            Analysis {
+                winning_holds: 1,
+                optimal_hold: 4,
+                best_distance: 108,
+            }
+        );
+    }
+
+    #[test]
+    fn can_report_overflow_when_scanning() {
+        let model = Model {
+            charge: Charge::Quadratic { rate: i64::MAX },
+            drag: 0,
+        };
+        let race = GameResult {
+            time: 1 << 40,
+            distance: 0,
+        };
+
+        assert_eq!(
+            model.analyse(&race).unwrap_err().get_reason(),
+            "distance overflows when holding for 4097"
+        );
+    }
+
+    #[test]
+    fn can_report_overflow_for_linear_models() {
+        let model = Model {
+            charge: Charge::Linear { rate: 1 << 32 },
+            drag: 0,
+        };
+        let race = GameResult {
+            time: 1 << 40,
+            distance: 0,
+        };
+
+        assert_eq!(
+            model.analyse(&race).unwrap_err().get_reason(),
+            "distance overflows with a charge rate of 4294967296"
+        );
+    }
+
+    #[test]
+    fn standard_model_agrees_with_part_1() {
+        let race = GameResult {
+            time: 30,
+            distance: 200,
+        };
+
+        let analysis = Model::standard().analyse(&race).unwrap();
+
+        assert_eq!(analysis.winning_holds, number_of_better_solutions(&race));
+        assert_eq!(analysis.optimal_hold, 15);
+        assert_eq!(analysis.best_distance, 225);
+    }
 }
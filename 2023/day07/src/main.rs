@@ -1,5 +1,6 @@
-use crate::day_07::{parse, solve};
+use crate::day_07::{parse, solve, Hand, HandType, Rules};
 use common::file;
+use std::env;
 
 fn main() {
     let file = String::from("./input.txt");
@@ -7,168 +8,341 @@ fn main() {
 
     let results = parse(&lines);
 
-    println!("part 1: {}", solve(&results, false));
-    println!("part 2: {}", solve(&results, true));
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("variant") {
+        let rules = variant_rules(&args[2..]);
+        print_types(&results, &rules);
+        println!("variant: {}", solve(&results, &rules).unwrap());
+        return;
+    }
+
+    println!("part 1: {}", solve(&results, &Rules::standard()).unwrap());
+    println!(
+        "part 2: {}",
+        solve(&results, &Rules::with_jokers()).unwrap()
+    );
+}
+
+/// Rules from `<ranking> <wildcards> <hand size> [name=groups ...]`, e.g.
+/// `J23456789TQKA J 5 "five of a kind=5" "full house=3,2" "high card="`.
+fn variant_rules(args: &[String]) -> Rules {
+    let mut rules = Rules::standard()
+        .with_ranking(&args[0])
+        .with_wildcards(args.get(1).map_or("", |w| w.as_str()))
+        .with_hand_size(args.get(2).map_or(5, |s| s.parse().unwrap()));
+
+    if args.len() > 3 {
+        rules = rules.with_types(
+            args[3..]
+                .iter()
+                .map(|t| {
+                    let (name, groups) = t.split_once('=').unwrap();
+                    let groups: Vec<u8> = groups
+                        .split(',')
+                        .filter(|g| !g.is_empty())
+                        .map(|g| g.parse().unwrap())
+                        .collect();
+                    HandType::new(name, &groups)
+                })
+                .collect(),
+        );
+    }
+
+    rules
+}
+
+fn print_types(hands: &[Hand], rules: &Rules) {
+    for hand in hands {
+        rules.validate(hand).unwrap();
+    }
+
+    for hand_type in rules.types.iter() {
+        let count = hands
+            .iter()
+            .filter(|h| h.get_type(rules) == hand_type)
+            .count();
+        println!("{}: {}", hand_type.name, count);
+    }
 }
 
 mod day_07 {
     use std::{cmp::Ordering, collections::HashMap};
 
-    use common::core::IntoArr;
+    use common::core::{Error, Result};
 
-    #[derive(PartialEq, Debug)]
-    pub enum HandType {
-        FiveOfAKind,
-        FourOfAKind,
-        FullHouse,
-        ThreeOfAKind,
-        TwoPair,
-        OnePair,
-        HighCard,
+    /// A kind of hand, matched by its largest groups of equal cards, e.g. a full house is a
+    /// group of at least three then a group of at least two.
+    #[derive(PartialEq, Eq, Clone, Debug)]
+    pub struct HandType {
+        pub name: String,
+        pub groups: Vec<u8>,
     }
 
     impl HandType {
-        pub fn cmp(&self, other: &Self) -> Ordering {
-            rank_hand_type(self).cmp(&rank_hand_type(other))
+        pub fn new(name: &str, groups: &[u8]) -> Self {
+            HandType {
+                name: String::from(name),
+                groups: groups.to_vec(),
+            }
         }
-    }
 
-    fn rank_hand_type(hand_type: &HandType) -> u8 {
-        match hand_type {
-            HandType::FiveOfAKind => 6,
-            HandType::FourOfAKind => 5,
-            HandType::FullHouse => 4,
-            HandType::ThreeOfAKind => 3,
-            HandType::TwoPair => 2,
-            HandType::OnePair => 1,
-            HandType::HighCard => 0,
+        fn matches(&self, shape: &[u8]) -> bool {
+            self.groups.len() <= shape.len()
+                && self.groups.iter().zip(shape.iter()).all(|(g, s)| s >= g)
         }
     }
 
-    #[derive(Debug)]
-    pub struct Hand {
-        pub cards: [char; 5],
-        pub bid: i32,
+    /// How a game of camel cards is played.
+    #[derive(Clone, Debug)]
+    pub struct Rules {
+        /// Cards from weakest to strongest.
+        pub ranking: Vec<char>,
+        /// Cards that stand in for whichever card makes the best hand.
+        pub wildcards: Vec<char>,
+        pub hand_size: usize,
+        /// Hand types from strongest to weakest; a hand takes the first type it matches.
+        pub types: Vec<HandType>,
     }
 
-    impl Hand {
-        pub fn get_type(&self, enable_wildcard: bool) -> HandType {
-            const WILDCARD: char = 'J';
-
-            let mut card_count = HashMap::<char, u8>::new();
-            for card in self.cards.iter() {
-                card_count.entry(*card).and_modify(|c| *c += 1).or_insert(1);
+    impl Rules {
+        pub fn standard() -> Self {
+            Rules {
+                ranking: "23456789TJQKA".chars().collect(),
+                wildcards: vec![],
+                hand_size: 5,
+                types: vec![
+                    HandType::new("five of a kind", &[5]),
+                    HandType::new("four of a kind", &[4]),
+                    HandType::new("full house", &[3, 2]),
+                    HandType::new("three of a kind", &[3]),
+                    HandType::new("two pair", &[2, 2]),
+                    HandType::new("one pair", &[2]),
+                    HandType::new("high card", &[]),
+                ],
             }
+        }
 
-            let mut count_of_counts = HashMap::<u8, u8>::new();
-            for (c, count) in card_count.iter() {
-                if enable_wildcard && *c == WILDCARD {
-                    continue;
-                }
+        /// Part two: `J` is a joker, and the weakest card on its own.
+        pub fn with_jokers() -> Self {
+            Self::standard()
+                .with_ranking("J23456789TQKA")
+                .with_wildcards("J")
+        }
 
-                count_of_counts
-                    .entry(*count)
-                    .and_modify(|c| *c += 1)
-                    .or_insert(1);
-            }
+        pub fn with_ranking(mut self, weakest_first: &str) -> Self {
+            self.ranking = weakest_first.chars().collect();
+            self
+        }
 
-            let mut hand_type = Hand::get_type_from_count_of_counts(&count_of_counts);
+        pub fn with_wildcards(mut self, wildcards: &str) -> Self {
+            self.wildcards = wildcards.chars().collect();
+            self
+        }
 
-            if !enable_wildcard {
-                return hand_type;
-            }
+        pub fn with_hand_size(mut self, hand_size: usize) -> Self {
+            self.hand_size = hand_size;
+            self
+        }
 
-            let wildcard_count = card_count.get(&WILDCARD).unwrap_or(&0).clone();
-            for _ in 0..wildcard_count {
-                hand_type = Hand::apply_wildcard(hand_type);
-            }
+        pub fn with_types(mut self, strongest_first: Vec<HandType>) -> Self {
+            self.types = strongest_first;
+            self
+        }
 
-            hand_type
+        pub fn rank_card(&self, card: char) -> Option<usize> {
+            self.ranking.iter().position(|c| *c == card)
         }
 
-        fn get_type_from_count_of_counts(count_of_counts: &HashMap<u8, u8>) -> HandType {
-            if count_of_counts.get(&5).is_some() {
-                return HandType::FiveOfAKind;
+        /// Check a hand can be played, so it can be typed and compared without failing.
+        pub fn validate(&self, hand: &Hand) -> Result<()> {
+            if hand.cards.len() != self.hand_size {
+                return Err(Error::new(&format!(
+                    "hand {} has {} cards, not {}",
+                    hand,
+                    hand.cards.len(),
+                    self.hand_size
+                )));
             }
 
-            if count_of_counts.get(&4).is_some() {
-                return HandType::FourOfAKind;
+            if let Some(card) = hand.cards.iter().find(|c| self.rank_card(**c).is_none()) {
+                return Err(Error::new(&format!(
+                    "hand {} has unranked card '{}'",
+                    hand, card
+                )));
             }
 
-            let three_count = count_of_counts.get(&3);
-            if three_count.is_some() {
-                if count_of_counts.get(&2).is_some() {
-                    return HandType::FullHouse;
-                }
-
-                return HandType::ThreeOfAKind;
+            if self.key_bits() > u64::BITS {
+                return Err(Error::new(&format!(
+                    "{} cards of {} ranks don't fit in a sort key",
+                    self.hand_size,
+                    self.ranking.len()
+                )));
             }
 
-            let pair_count = count_of_counts.get(&2).unwrap_or(&0);
-            if *pair_count == 2 {
-                return HandType::TwoPair;
-            }
-            if *pair_count == 1 {
-                return HandType::OnePair;
+            if self.hand_type_index(hand).is_none() {
+                return Err(Error::new(&format!("hand {} matches no hand type", hand)));
             }
 
-            return HandType::HighCard;
+            Ok(())
         }
 
-        fn apply_wildcard(hand_type: HandType) -> HandType {
-            match hand_type {
-                HandType::FiveOfAKind => HandType::FiveOfAKind,
-                HandType::FourOfAKind => HandType::FiveOfAKind,
-                HandType::FullHouse => HandType::FourOfAKind,
-                HandType::ThreeOfAKind => HandType::FourOfAKind,
-                HandType::TwoPair => HandType::FullHouse,
-                HandType::OnePair => HandType::ThreeOfAKind,
-                HandType::HighCard => HandType::OnePair,
+        /// Type and sort key a hand once, so sorting doesn't recount its cards. The hand must be
+        /// valid under the rules.
+        pub fn rank<'a>(&'a self, hand: &'a Hand) -> RankedHand<'a> {
+            let index = self.hand_type_index(hand).unwrap();
+            let hand_type = RankedType {
+                strength: self.types.len() - 1 - index,
+                hand_type: &self.types[index],
+            };
+
+            let bits = bits_for(self.ranking.len());
+            let key = hand
+                .cards
+                .iter()
+                .fold(hand_type.strength as u64, |key, card| {
+                    (key << bits) | self.rank_card(*card).unwrap() as u64
+                });
+
+            RankedHand {
+                key,
+                hand_type,
+                hand,
             }
         }
 
-        pub fn cmp(&self, other: &Self, enable_wildcard: bool) -> Ordering {
-            let type_comparison = self
-                .get_type(enable_wildcard)
-                .cmp(&other.get_type(enable_wildcard));
+        /// The type's strength above the card ranks, most significant card first.
+        fn key_bits(&self) -> u32 {
+            bits_for(self.types.len()) + bits_for(self.ranking.len()) * self.hand_size as u32
+        }
 
-            if type_comparison != Ordering::Equal {
-                return type_comparison;
+        /// The position of the hand's type in `types`, so lower is stronger.
+        fn hand_type_index(&self, hand: &Hand) -> Option<usize> {
+            let mut card_count = HashMap::<char, u8>::new();
+            let mut wildcard_count = 0;
+            for card in hand.cards.iter() {
+                if self.wildcards.contains(card) {
+                    wildcard_count += 1;
+                } else {
+                    *card_count.entry(*card).or_insert(0) += 1;
+                }
             }
 
-            for (self_card, other_card) in self.cards.iter().zip(other.cards.iter()) {
-                let char_comparison = cmp_card(self_card, other_card, enable_wildcard);
-                if char_comparison != Ordering::Equal {
-                    return char_comparison;
+            let mut shape: Vec<u8> = card_count.into_values().collect();
+            shape.sort_unstable_by(|a, b| b.cmp(a));
+
+            self.best_type_index(shape, wildcard_count)
+        }
+
+        /// Try every way of spending the wildcards: each joins an existing group or starts a
+        /// new one.
+        fn best_type_index(&self, shape: Vec<u8>, wildcards: u8) -> Option<usize> {
+            if wildcards == 0 {
+                return self.types.iter().position(|t| t.matches(&shape));
+            }
+
+            let mut options = vec![];
+            for i in 0..shape.len() {
+                if i > 0 && shape[i] == shape[i - 1] {
+                    continue;
                 }
+
+                let mut option = shape.clone();
+                option[i] += 1;
+                option.sort_unstable_by(|a, b| b.cmp(a));
+                options.push(option);
             }
 
-            Ordering::Equal
+            let mut new_group = shape;
+            new_group.push(1);
+            options.push(new_group);
+
+            options
+                .into_iter()
+                .filter_map(|option| self.best_type_index(option, wildcards - 1))
+                .min()
         }
     }
 
-    pub fn cmp_card(left: &char, right: &char, enable_wildcard: bool) -> Ordering {
-        const ORDERED: [char; 13] = [
-            '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', 'A',
-        ];
+    fn bits_for(values: usize) -> u32 {
+        usize::BITS - values.saturating_sub(1).leading_zeros()
+    }
 
-        const ORDERED_FOR_WILDCARD: [char; 13] = [
-            'J', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'Q', 'K', 'A',
-        ];
+    /// A hand type with its strength under some rules, ordered weakest first.
+    #[derive(Debug)]
+    pub struct RankedType<'a> {
+        pub strength: usize,
+        pub hand_type: &'a HandType,
+    }
 
-        let order = match enable_wildcard {
-            true => &ORDERED_FOR_WILDCARD,
-            false => &ORDERED,
-        };
+    impl PartialEq for RankedType<'_> {
+        fn eq(&self, other: &Self) -> bool {
+            self.strength == other.strength
+        }
+    }
 
-        let left_rank = order.iter().position(|c| *c == *left).unwrap();
-        let right_rank = order.iter().position(|c| *c == *right).unwrap();
+    impl Eq for RankedType<'_> {}
 
-        return left_rank.cmp(&right_rank);
+    impl PartialOrd for RankedType<'_> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
     }
 
-    pub fn parse(lines: &Vec<String>) -> Vec<Hand> {
-        lines.iter().map(|l| parse_hand(&l)).collect()
+    impl Ord for RankedType<'_> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.strength.cmp(&other.strength)
+        }
+    }
+
+    /// A hand with its packed sort key under some rules, ordered weakest first.
+    #[derive(Debug)]
+    pub struct RankedHand<'a> {
+        pub key: u64,
+        pub hand_type: RankedType<'a>,
+        pub hand: &'a Hand,
+    }
+
+    impl PartialEq for RankedHand<'_> {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for RankedHand<'_> {}
+
+    impl PartialOrd for RankedHand<'_> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for RankedHand<'_> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.key.cmp(&other.key)
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct Hand {
+        pub cards: Vec<char>,
+        pub bid: i32,
+    }
+
+    impl std::fmt::Display for Hand {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.cards.iter().collect::<String>())
+        }
+    }
+
+    impl Hand {
+        /// The strongest type the hand can make. The hand must be valid under the rules.
+        pub fn get_type<'a>(&'a self, rules: &'a Rules) -> &'a HandType {
+            rules.rank(self).hand_type.hand_type
+        }
+    }
+
+    pub fn parse(lines: &[String]) -> Vec<Hand> {
+        lines.iter().map(|l| parse_hand(l)).collect()
     }
 
     pub fn parse_hand(line: &str) -> Hand {
@@ -179,20 +353,24 @@ mod day_07 {
         }
     }
 
-    pub fn to_cards(s: &str) -> [char; 5] {
-        s.chars().collect::<Vec<char>>().into_arr()
+    pub fn to_cards(s: &str) -> Vec<char> {
+        s.chars().collect()
     }
 
-    pub fn solve(hands: &Vec<Hand>, enable_wildcard: bool) -> i32 {
-        let mut sorted_winner_last: Vec<&Hand> = hands.iter().collect::<Vec<&Hand>>();
-        sorted_winner_last.sort_by(|l, r| (*l).cmp(*r, enable_wildcard));
+    pub fn solve(hands: &[Hand], rules: &Rules) -> Result<i32> {
+        for hand in hands {
+            rules.validate(hand)?;
+        }
+
+        let mut sorted_winner_last: Vec<RankedHand> = hands.iter().map(|h| rules.rank(h)).collect();
+        sorted_winner_last.sort_unstable();
 
         let mut total = 0;
         for (i, h) in sorted_winner_last.iter().enumerate() {
-            total += (i as i32 + 1) * h.bid;
+            total += (i as i32 + 1) * h.hand.bid;
         }
 
-        return total;
+        Ok(total)
     }
 }
 
@@ -200,7 +378,7 @@ mod day_07 {
 mod tests {
     use crate::{day_07::*, tests::hand_builder::HandBuilder};
     use common::file;
-    use std::cmp::Ordering;
+    use std::cmp::{Ordering, Reverse};
     use test_case::test_case;
 
     #[test]
@@ -210,10 +388,9 @@ mod tests {
 
         let hands = parse(&lines);
 
-        assert_eq!(solve(&hands, false), 6440);
+        assert_eq!(solve(&hands, &Rules::standard()).unwrap(), 6440);
     }
 
-    #[ignore]
     #[test]
     fn can_solve_part_2_for_example_file() {
         let file = String::from("./example.txt");
@@ -221,17 +398,17 @@ mod tests {
 
         let hands = parse(&lines);
 
-        assert_eq!(solve(&hands, true), 5905);
+        assert_eq!(solve(&hands, &Rules::with_jokers()).unwrap(), 5905);
     }
 
     #[test]
     fn can_parse_hand() {
         let line = "32T3K 765";
 
-        let hand = parse_hand(&line);
+        let hand = parse_hand(line);
 
         assert_eq!(hand.bid, 765);
-        assert_eq!(hand.cards, ['3', '2', 'T', '3', 'K']);
+        assert_eq!(hand.cards, vec!['3', '2', 'T', '3', 'K']);
     }
 
     #[test]
@@ -259,19 +436,19 @@ mod tests {
             },
         ];
 
-        assert_eq!(solve(&hands, false), 6440);
+        assert_eq!(solve(&hands, &Rules::standard()).unwrap(), 6440);
     }
 
     #[test]
     fn can_order_hands_by_type() {
         // Not happy with this builder and test case is probably better for this kind of problem anyway
-        let five_of_a_kind = HandBuilder::new().with_type(HandType::FiveOfAKind).build();
-        let four_of_a_kind = HandBuilder::new().with_type(HandType::FourOfAKind).build();
-        let full_house = HandBuilder::new().with_type(HandType::FullHouse).build();
-        let three_of_a_kind = HandBuilder::new().with_type(HandType::ThreeOfAKind).build();
-        let two_pair = HandBuilder::new().with_type(HandType::TwoPair).build();
-        let one_pair = HandBuilder::new().with_type(HandType::OnePair).build();
-        let high = HandBuilder::new().with_type(HandType::HighCard).build();
+        let five_of_a_kind = HandBuilder::new().with_type("five of a kind").build();
+        let four_of_a_kind = HandBuilder::new().with_type("four of a kind").build();
+        let full_house = HandBuilder::new().with_type("full house").build();
+        let three_of_a_kind = HandBuilder::new().with_type("three of a kind").build();
+        let two_pair = HandBuilder::new().with_type("two pair").build();
+        let one_pair = HandBuilder::new().with_type("one pair").build();
+        let high = HandBuilder::new().with_type("high card").build();
 
         let unordered_hands = [
             &high,
@@ -283,8 +460,9 @@ mod tests {
             &one_pair,
         ];
 
-        let mut sorted = unordered_hands.iter().map(|h| *h).collect::<Vec<&Hand>>();
-        sorted.sort_by(|l, r| r.cmp(l, false));
+        let mut sorted = unordered_hands.to_vec();
+        let rules = Rules::standard();
+        sorted.sort_by_key(|h| Reverse(rules.rank(h)));
 
         assert_ref_eq(sorted[0], &five_of_a_kind);
         assert_ref_eq(sorted[1], &four_of_a_kind);
@@ -295,39 +473,96 @@ mod tests {
         assert_ref_eq(sorted[6], &high);
     }
 
-    #[test_case("222JJ", HandType::FiveOfAKind; "five of a kind")]
-    #[test_case("222J3", HandType::FourOfAKind; "four of a kind")]
-    #[test_case("J2233", HandType::FullHouse; "gives full house")]
-    #[test_case("K2JJ3", HandType::ThreeOfAKind; "gives three of a kind")]
-    #[test_case("22J34", HandType::ThreeOfAKind; "gives three of a kind, not a full house")] // ie don't double count the J
-    #[test_case("J2345", HandType::OnePair; "gives pair")]
-    fn can_find_type_with_wildcard(cards: &str, hand_type: HandType) {
+    #[test_case("222JJ", "five of a kind"; "five of a kind")]
+    #[test_case("222J3", "four of a kind"; "four of a kind")]
+    #[test_case("J2233", "full house"; "gives full house")]
+    #[test_case("K2JJ3", "three of a kind"; "gives three of a kind")]
+    #[test_case("22J34", "three of a kind"; "gives three of a kind, not a full house")] // ie don't double count the J
+    #[test_case("J2345", "one pair"; "gives pair")]
+    #[test_case("JJJJJ", "five of a kind"; "all wildcards")]
+    fn can_find_type_with_wildcard(cards: &str, hand_type: &str) {
+        let rules = Rules::with_jokers();
+
         assert_eq!(
             HandBuilder::new()
                 .with_cards(to_cards(cards))
                 .build()
-                .get_type(true),
+                .get_type(&rules)
+                .name,
             hand_type
         );
     }
 
-    #[test_case("2KKKK", "33332", false)]
-    #[test_case("22228", "22229", false)]
-    #[test_case("KAAAA", "AKKKK", false)]
-    #[test_case("2AAAA", "JKKKK", false)]
-    #[test_case("JAAA2", "2KKKJ", true; "J is weak when wildcard")]
+    #[test]
+    fn can_play_variant_rules() {
+        let rules = Rules::standard()
+            .with_ranking("*ABCD")
+            .with_wildcards("*D")
+            .with_hand_size(4)
+            .with_types(vec![
+                HandType::new("all four", &[4]),
+                HandType::new("two pair", &[2, 2]),
+                HandType::new("three", &[3]),
+                HandType::new("nothing", &[]),
+            ]);
+
+        let hand = |cards: &str| HandBuilder::new().with_cards(to_cards(cards)).build();
+
+        // A wildcard pairs the C rather than making three, as two pair ranks higher here
+        assert_eq!(hand("AA*C").get_type(&rules).name, "two pair");
+        assert_eq!(hand("AAAC").get_type(&rules).name, "three");
+        assert_eq!(hand("ABCA").get_type(&rules).name, "nothing");
+        assert_eq!(hand("AAD*").get_type(&rules).name, "all four");
+        assert!(rules.rank(&hand("AAAC")) < rules.rank(&hand("AA*C")));
+
+        let hands = vec![hand("ABCDD")];
+        assert_eq!(
+            solve(&hands, &rules).unwrap_err().get_reason(),
+            "hand ABCDD has 5 cards, not 4"
+        );
+        let hands = vec![hand("ABCE")];
+        assert_eq!(
+            solve(&hands, &rules).unwrap_err().get_reason(),
+            "hand ABCE has unranked card 'E'"
+        );
+    }
+
+    #[test_case("2KKKK", "33332", Rules::standard())]
+    #[test_case("22228", "22229", Rules::standard())]
+    #[test_case("KAAAA", "AKKKK", Rules::standard())]
+    #[test_case("2AAAA", "JKKKK", Rules::standard())]
+    #[test_case("JAAA2", "2KKKJ", Rules::with_jokers(); "J is weak when wildcard")]
     fn can_order_hands_of_the_same_type_based_on_leading_numbers(
         lower: &str,
         higher: &str,
-        enable_wildcard: bool,
+        rules: Rules,
     ) {
         let lower_hand = HandBuilder::new().with_cards(to_cards(lower)).build();
         let higher_hand = HandBuilder::new().with_cards(to_cards(higher)).build();
 
         assert_eq!(
-            higher_hand.cmp(&lower_hand, enable_wildcard),
+            rules.rank(&higher_hand).cmp(&rules.rank(&lower_hand)),
             Ordering::Greater
         );
+        assert!(rules.rank(&higher_hand).hand_type == rules.rank(&lower_hand).hand_type);
+    }
+
+    #[test]
+    fn can_pack_sort_key() {
+        let rules = Rules::standard();
+        let hand = |cards: &str| HandBuilder::new().with_cards(to_cards(cards)).build();
+
+        assert_eq!(rules.rank(&hand("23456")).key, 0x0_01234);
+        assert_eq!(rules.rank(&hand("AAAAA")).key, 0x6_ccccc);
+
+        let long_hands = rules.with_hand_size(20);
+        assert_eq!(
+            long_hands
+                .validate(&hand("23456789TJQKA2345678"))
+                .unwrap_err()
+                .get_reason(),
+            "20 cards of 13 ranks don't fit in a sort key"
+        );
     }
 
     fn assert_ref_eq(l: &Hand, r: &Hand) {
@@ -337,39 +572,39 @@ mod tests {
 
     // bit over the top to make a builder. Especially since mut hand is an option. But I do this a lot in C' i wanted to see how it is
     mod hand_builder {
-        use crate::day_07::{Hand, HandType};
+        use crate::day_07::Hand;
 
         pub struct HandBuilder {
-            pub cards: [char; 5],
+            pub cards: Vec<char>,
             pub bid: i32,
         }
 
         impl HandBuilder {
             pub fn new() -> HandBuilder {
                 HandBuilder {
-                    cards: ['1', '2', '3', '4', '5'],
+                    cards: vec!['2', '3', '4', '5', '8'],
                     bid: 10,
                 }
             }
 
-            pub fn with_cards(mut self, cards: [char; 5]) -> Self {
+            pub fn with_cards(mut self, cards: Vec<char>) -> Self {
                 self.cards = cards;
                 self
             }
 
-            pub fn with_type(mut self, hand_type: HandType) -> Self {
+            pub fn with_type(mut self, hand_type: &str) -> Self {
                 let a = '6';
                 let b = '7';
                 match hand_type {
-                    HandType::FiveOfAKind => self.cards = [a, a, a, a, a],
-                    HandType::FourOfAKind => self.cards = [a, a, a, a, self.cards[4]],
-                    HandType::FullHouse => self.cards = [a, a, a, b, b],
-                    HandType::ThreeOfAKind => self.cards = [a, a, a, self.cards[3], self.cards[4]],
-                    HandType::TwoPair => self.cards = [a, a, b, b, self.cards[4]],
-                    HandType::OnePair => {
-                        self.cards = [a, a, self.cards[2], self.cards[3], self.cards[4]]
+                    "five of a kind" => self.cards = vec![a, a, a, a, a],
+                    "four of a kind" => self.cards = vec![a, a, a, a, self.cards[4]],
+                    "full house" => self.cards = vec![a, a, a, b, b],
+                    "three of a kind" => self.cards = vec![a, a, a, self.cards[3], self.cards[4]],
+                    "two pair" => self.cards = vec![a, a, b, b, self.cards[4]],
+                    "one pair" => {
+                        self.cards = vec![a, a, self.cards[2], self.cards[3], self.cards[4]]
                     }
-                    HandType::HighCard => (),
+                    _ => (),
                 };
 
                 self
@@ -377,7 +612,7 @@ mod tests {
 
             pub fn build(&self) -> Hand {
                 Hand {
-                    cards: self.cards,
+                    cards: self.cards.clone(),
                     bid: self.bid,
                 }
             }
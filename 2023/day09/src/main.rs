@@ -1,6 +1,8 @@
+use std::env;
+
 use common::file;
 
-use crate::oasis::{parse, part_1, part_2};
+use crate::oasis::{parse, part_1, part_2, Extrapolator};
 
 fn main() {
     let file = String::from("./input.txt");
@@ -8,85 +10,295 @@ fn main() {
 
     let sequences = parse(&lines);
 
-    println!("part 1: {}", part_1(&sequences));
-    println!("part 2: {}", part_2(&sequences));
+    if env::args().nth(1).as_deref() == Some("closed-form") {
+        return print_closed_forms(&sequences);
+    }
+
+    println!("part 1: {}", part_1(&sequences).unwrap());
+    println!("part 2: {}", part_2(&sequences).unwrap());
+}
+
+/// Print the polynomial behind every line, with n counting from zero at the line's first value.
+fn print_closed_forms(sequences: &[Vec<i64>]) {
+    for (i, sequence) in sequences.iter().enumerate() {
+        match Extrapolator::fit(sequence).and_then(|e| e.polynomial()) {
+            Ok(polynomial) => println!(
+                "line {}: degree {}: {}",
+                i + 1,
+                polynomial.degree(),
+                polynomial
+            ),
+            Err(e) => println!("line {}: {}", i + 1, e.get_reason()),
+        }
+    }
 }
 
 mod oasis {
-    // could remove double work calculate_diff_layers, and not bother finding the middle values etc
+    use common::core::{Error, Result};
+    use std::fmt;
 
-    pub fn part_1(sequences: &Vec<Vec<i32>>) -> i32 {
-        sequences
-            .iter()
-            .fold(0, |acc, n| acc + infer_next_number(n))
+    pub fn part_1(sequences: &[Vec<i64>]) -> Result<i128> {
+        sequences.iter().map(|s| infer_next_number(s)).sum()
     }
 
-    pub fn part_2(sequences: &Vec<Vec<i32>>) -> i32 {
-        sequences
-            .iter()
-            .fold(0, |acc, n| acc + infer_preceeding_number(n))
+    pub fn part_2(sequences: &[Vec<i64>]) -> Result<i128> {
+        sequences.iter().map(|s| infer_preceeding_number(s)).sum()
     }
 
-    pub fn infer_preceeding_number(sequence: &[i32]) -> i32 {
-        let rev: Vec<i32> = sequence.iter().rev().map(|v| *v).collect();
+    pub fn infer_preceeding_number(sequence: &[i64]) -> Result<i128> {
+        Extrapolator::fit(sequence)?.at(-1)
+    }
 
-        infer_next_number(&rev)
+    pub fn infer_next_number(sequence: &[i64]) -> Result<i128> {
+        Extrapolator::fit(sequence)?.at(sequence.len() as i128)
     }
 
-    pub fn infer_next_number(sequence: &[i32]) -> i32 {
-        let layers = calculate_diff_layers(sequence);
+    /// The polynomial through a sequence, kept as the first value of each difference layer
+    /// so it can be evaluated anywhere with Newton's forward difference formula.
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Extrapolator {
+        leading_diffs: Vec<i128>,
+    }
 
-        /*
-           for given example
-           (2 + 4 + 9) + 30 = 45, which is the next answer
-        */
-        let diff = layers.iter().fold(0, |acc, f| acc + f.last().unwrap());
+    impl Extrapolator {
+        pub fn fit(sequence: &[i64]) -> Result<Self> {
+            let layers = calculate_diff_layers(sequence)?;
 
-        sequence.last().unwrap() + diff
-    }
+            Ok(Extrapolator {
+                leading_diffs: layers.iter().map(|l| l[0]).collect(),
+            })
+        }
 
-    fn calculate_diff_layers(sequence: &[i32]) -> Vec<Vec<i32>> {
-        let mut last_layer = sequence;
-        let mut layers: Vec<Vec<i32>> = Vec::new();
-        loop {
+        /// The value at `index`, where the sequence's first value is at index zero. Works
+        /// backwards from negative indices too.
+        pub fn at(&self, index: i128) -> Result<i128> {
             /*
-                eg for
-            10  13  16  21  30
-                find:
-              3   3   5   9
-                0   2   4
-                  2   2
-                    0
-                */
-            let layer = calculate_diffs(&last_layer);
-            if layer.iter().all(|n| *n == 0) {
-                break;
+               value(index) = sum of leading_diff[j] * (index choose j)
+               where (index choose j) = index * (index - 1) ... (index - j + 1) / j!
+               Each step's division is exact, as any j consecutive integers divide by j!
+            */
+            let overflow = || Error::new(&format!("value at index {} overflows i128", index));
+
+            let mut value = 0_i128;
+            let mut choose = 1_i128;
+            for (j, diff) in self.leading_diffs.iter().enumerate() {
+                if j > 0 {
+                    choose = index
+                        .checked_sub(j as i128)
+                        .and_then(|n| n.checked_add(1))
+                        .and_then(|n| choose.checked_mul(n))
+                        .ok_or_else(overflow)?
+                        / j as i128;
+                }
+                value = diff
+                    .checked_mul(choose)
+                    .and_then(|term| value.checked_add(term))
+                    .ok_or_else(overflow)?;
             }
 
-            layers.push(layer);
-            last_layer = &layers.last().unwrap();
+            Ok(value)
+        }
+
+        /// Expand the Newton form into coefficients of powers of n.
+        pub fn polynomial(&self) -> Result<Polynomial> {
+            let overflow = || Error::new("polynomial coefficients overflow i128");
+
+            let mut coefficients = vec![Rational::ZERO; self.leading_diffs.len()];
+            // n (n - 1) ... (n - j + 1), lowest power first
+            let mut falling = vec![1_i128];
+            let mut factorial = 1_i128;
+            for (j, diff) in self.leading_diffs.iter().enumerate() {
+                if j > 0 {
+                    let mut next = vec![0; j + 1];
+                    for (power, c) in falling.iter().enumerate() {
+                        next[power + 1] += c;
+                        next[power] -= c * (j as i128 - 1);
+                    }
+                    falling = next;
+                    factorial = factorial.checked_mul(j as i128).ok_or_else(overflow)?;
+                }
+
+                for (power, c) in falling.iter().enumerate() {
+                    let term = c
+                        .checked_mul(*diff)
+                        .and_then(|n| Rational::new(n, factorial))
+                        .ok_or_else(overflow)?;
+                    coefficients[power] = coefficients[power].add(term).ok_or_else(overflow)?;
+                }
+            }
+
+            Ok(Polynomial { coefficients })
+        }
+    }
+
+    /// A fraction in lowest terms with a positive denominator.
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub struct Rational {
+        pub numerator: i128,
+        pub denominator: i128,
+    }
+
+    impl Rational {
+        pub const ZERO: Rational = Rational {
+            numerator: 0,
+            denominator: 1,
+        };
+
+        pub fn new(numerator: i128, denominator: i128) -> Option<Self> {
+            if denominator == 0 {
+                return None;
+            }
+
+            let divisor = gcd(numerator, denominator) * denominator.signum();
+            Some(Rational {
+                numerator: numerator.checked_div(divisor)?,
+                denominator: denominator.checked_div(divisor)?,
+            })
+        }
+
+        pub fn add(self, other: Rational) -> Option<Self> {
+            let lcm = (self.denominator / gcd(self.denominator, other.denominator))
+                .checked_mul(other.denominator)?;
+
+            Rational::new(
+                self.numerator
+                    .checked_mul(lcm / self.denominator)?
+                    .checked_add(other.numerator.checked_mul(lcm / other.denominator)?)?,
+                lcm,
+            )
+        }
+    }
+
+    impl fmt::Display for Rational {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.denominator {
+                1 => write!(f, "{}", self.numerator),
+                _ => write!(f, "{}/{}", self.numerator, self.denominator),
+            }
+        }
+    }
+
+    fn gcd(a: i128, b: i128) -> i128 {
+        let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+        while b != 0 {
+            (a, b) = (b, a % b);
         }
+        a as i128
+    }
 
-        layers
+    /// Exact coefficients of powers of n, lowest power first.
+    #[derive(PartialEq, Eq, Debug)]
+    pub struct Polynomial {
+        pub coefficients: Vec<Rational>,
     }
 
-    fn calculate_diffs(sequence: &[i32]) -> Vec<i32> {
-        let mut diffs: Vec<i32> = Vec::new();
-        for i in 0..sequence.len() - 1 {
-            let diff = sequence[i + 1] - sequence[i];
-            diffs.push(diff);
+    impl Polynomial {
+        /// The highest power with a non-zero coefficient, or zero for the zero polynomial.
+        pub fn degree(&self) -> usize {
+            self.coefficients
+                .iter()
+                .rposition(|c| *c != Rational::ZERO)
+                .unwrap_or(0)
         }
+    }
+
+    /// Written highest power first, e.g. `n^2/2 + 3n/2 + 1`.
+    impl fmt::Display for Polynomial {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let terms = self
+                .coefficients
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, c)| **c != Rational::ZERO);
+
+            let mut first = true;
+            for (power, c) in terms {
+                let sign = match (c.numerator < 0, first) {
+                    (true, true) => "-",
+                    (true, false) => " - ",
+                    (false, true) => "",
+                    (false, false) => " + ",
+                };
+                let numerator = c.numerator.unsigned_abs();
+                let variable = match power {
+                    0 => String::new(),
+                    1 => String::from("n"),
+                    _ => format!("n^{}", power),
+                };
+
+                write!(f, "{}", sign)?;
+                match (numerator, power) {
+                    (1, 1..) => write!(f, "{}", variable)?,
+                    _ => write!(f, "{}{}", numerator, variable)?,
+                }
+                if c.denominator != 1 {
+                    write!(f, "/{}", c.denominator)?;
+                }
+                first = false;
+            }
 
-        diffs
+            if first {
+                write!(f, "0")?;
+            }
+            Ok(())
+        }
     }
 
-    pub fn parse_sequence(line: &str) -> Vec<i32> {
+    /// The sequence followed by each layer of differences, up to but not including the first
+    /// all-zero layer. A single value has no differences, so it is taken to be constant.
+    fn calculate_diff_layers(sequence: &[i64]) -> Result<Vec<Vec<i128>>> {
+        /*
+            eg for
+        10  13  16  21  30
+            find:
+          3   3   5   9
+            0   2   4
+              2   2
+                0
+        */
+        if sequence.is_empty() {
+            return Err(Error::new("can't extrapolate an empty sequence"));
+        }
+
+        let mut layers: Vec<Vec<i128>> = vec![sequence.iter().map(|n| *n as i128).collect()];
+        while !layers.last().unwrap().iter().all(|n| *n == 0) {
+            let layer = calculate_diffs(layers.last().unwrap())?;
+            if layer.is_empty() && sequence.len() == 1 {
+                return Ok(layers);
+            }
+            if layer.is_empty() {
+                return Err(Error::new(&format!(
+                    "sequence of {} never reaches an all-zero difference layer",
+                    sequence.len()
+                )));
+            }
+
+            layers.push(layer);
+        }
+
+        layers.pop();
+        Ok(layers)
+    }
+
+    fn calculate_diffs(sequence: &[i128]) -> Result<Vec<i128>> {
+        sequence
+            .windows(2)
+            .map(|pair| {
+                pair[1]
+                    .checked_sub(pair[0])
+                    .ok_or_else(|| Error::new("difference overflows i128"))
+            })
+            .collect()
+    }
+
+    pub fn parse_sequence(line: &str) -> Vec<i64> {
         line.split_whitespace()
             .filter_map(|s| s.parse().ok())
             .collect()
     }
 
-    pub fn parse(lines: &Vec<String>) -> Vec<Vec<i32>> {
+    pub fn parse(lines: &[String]) -> Vec<Vec<i64>> {
         lines.iter().map(|l| parse_sequence(l)).collect()
     }
 }
@@ -99,30 +311,99 @@ mod tests {
 
     #[test_case(vec![1,2,3,4], 5)]
     #[test_case(vec![6,56,106], 156)]
-    fn infer_next_number_works_for_linear(sequence: Vec<i32>, expected: i32) {
-        assert_eq!(oasis::infer_next_number(&sequence), expected);
+    fn infer_next_number_works_for_linear(sequence: Vec<i64>, expected: i128) {
+        assert_eq!(oasis::infer_next_number(&sequence).unwrap(), expected);
     }
 
     #[test_case(vec![1,3,6,10,15,21], 28)]
-    fn infer_next_number_works_for_geometric(sequence: Vec<i32>, expected: i32) {
-        assert_eq!(oasis::infer_next_number(&sequence), expected);
+    fn infer_next_number_works_for_geometric(sequence: Vec<i64>, expected: i128) {
+        assert_eq!(oasis::infer_next_number(&sequence).unwrap(), expected);
     }
 
     #[test_case(vec![1,2,3,4], 0)]
     #[test_case(vec![-1,0,1,2,3,4], -2)]
     #[test_case(vec![6,56,106], -44)]
-    fn infer_preceeding_number_works_for_linear(sequence: Vec<i32>, expected: i32) {
-        assert_eq!(oasis::infer_preceeding_number(&sequence), expected);
+    fn infer_preceeding_number_works_for_linear(sequence: Vec<i64>, expected: i128) {
+        assert_eq!(oasis::infer_preceeding_number(&sequence).unwrap(), expected);
     }
 
     #[test_case(vec![10,13,16,21,30,45], 5)]
     #[test_case(vec![5,10,13,16,21,30,45], -4)]
-    fn infer_preceeding_number_works_for_geometric(sequence: Vec<i32>, expected: i32) {
-        assert_eq!(oasis::infer_preceeding_number(&sequence), expected);
+    fn infer_preceeding_number_works_for_geometric(sequence: Vec<i64>, expected: i128) {
+        assert_eq!(oasis::infer_preceeding_number(&sequence).unwrap(), expected);
     }
 
     #[test_case(vec![13, 14, 13, 10, 5, -2, -11, -22, -35, -50, -67, -86, -107, -130, -155, -182, -211, -242, -275, -310, -347], 10)]
-    fn foo(sequence: Vec<i32>, expected: i32) {
-        assert_eq!(oasis::infer_preceeding_number(&sequence), expected);
+    fn foo(sequence: Vec<i64>, expected: i128) {
+        assert_eq!(oasis::infer_preceeding_number(&sequence).unwrap(), expected);
+    }
+
+    #[test]
+    fn can_extrapolate_far_in_both_directions() {
+        // n^2 + 1
+        let extrapolator = oasis::Extrapolator::fit(&[1, 2, 5, 10, 17]).unwrap();
+
+        assert_eq!(extrapolator.at(1000).unwrap(), 1_000_001);
+        assert_eq!(extrapolator.at(-50).unwrap(), 2501);
+    }
+
+    #[test]
+    fn can_extrapolate_past_i64() {
+        let extrapolator = oasis::Extrapolator::fit(&[0, 1_000_000_000, 2_000_000_000]).unwrap();
+
+        assert_eq!(
+            extrapolator.at(100_000_000_000_000).unwrap(),
+            100_000_000_000_000_000_000_000
+        );
+        assert!(extrapolator.at(i128::MAX / 2).is_err());
+        assert!(extrapolator.at(i128::MIN).is_err());
+    }
+
+    #[test]
+    fn treats_a_single_value_as_constant() {
+        assert_eq!(oasis::infer_next_number(&[5]).unwrap(), 5);
+        assert_eq!(oasis::infer_preceeding_number(&[5]).unwrap(), 5);
+        assert_eq!(oasis::infer_next_number(&[0]).unwrap(), 0);
+    }
+
+    #[test]
+    fn can_report_sequence_without_zero_layer() {
+        let error = oasis::Extrapolator::fit(&[1, 2, 4, 8]).unwrap_err();
+
+        assert_eq!(
+            error.get_reason(),
+            "sequence of 4 never reaches an all-zero difference layer"
+        );
+        assert!(oasis::Extrapolator::fit(&[]).is_err());
+    }
+
+    #[test_case(vec![1,3,6,10], "n^2/2 + 3n/2 + 1", 2)]
+    #[test_case(vec![10,13,16,21,30,45], "n^3/3 - n^2 + 11n/3 + 10", 3)]
+    #[test_case(vec![5,3,1], "-2n + 5", 1)]
+    #[test_case(vec![-7,-7], "-7", 0)]
+    #[test_case(vec![0,0,0], "0", 0)]
+    fn can_print_closed_form(sequence: Vec<i64>, expected: &str, degree: usize) {
+        let polynomial = oasis::Extrapolator::fit(&sequence)
+            .unwrap()
+            .polynomial()
+            .unwrap();
+
+        assert_eq!(polynomial.to_string(), expected);
+        assert_eq!(polynomial.degree(), degree);
+    }
+
+    #[test]
+    fn polynomial_has_exact_rational_coefficients() {
+        let polynomial = oasis::Extrapolator::fit(&[1, 3, 6, 10])
+            .unwrap()
+            .polynomial()
+            .unwrap();
+
+        let coefficients: Vec<String> = polynomial
+            .coefficients
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(coefficients, vec!["1", "3/2", "1/2"]);
     }
 }
@@ -1,4 +1,5 @@
-use common::file;
+use common::{file, render::Recorder};
+use std::{env, time::Duration};
 
 use crate::pipe_maze::parse;
 
@@ -12,11 +13,23 @@ fn main() {
     let maze: pipe_maze::Maze = parse(&v);
 
     println!("part 1: {}", maze.distance_to_pipe_furthest_from_start());
+    match env::args().nth(1).as_deref() {
+        Some("show") => print!("{}", maze.scene().to_ansi()),
+        Some(path) => maze.scene().write(path).unwrap(),
+        None => (),
+    }
     println!("part 2: {}", maze.count_enclosed_area());
+
+    if let Some(path) = env::args().nth(2) {
+        let mut recorder = Recorder::every(100);
+        maze.record_search(&mut recorder);
+        maze.record_enclosure(&mut recorder);
+        recorder.write(&path, Duration::from_millis(50)).unwrap();
+    }
 }
 
 mod pipe_maze {
-    use colored::Colorize;
+    use common::render::{Colour, Layer, Recorder, Scene};
     use std::{
         collections::{HashMap, HashSet},
         ops::Add,
@@ -28,6 +41,12 @@ mod pipe_maze {
         pipes: HashMap<Coordinate, Pipe>,
     }
 
+    #[derive(Debug)]
+    pub struct Loop {
+        pub tiles: Vec<Coordinate>,
+        pub start_pipe: Pipe,
+    }
+
     #[derive(Debug)]
     struct Path {
         end: (Coordinate, Pipe),
@@ -37,134 +56,192 @@ mod pipe_maze {
 
     impl Maze {
         pub fn distance_to_pipe_furthest_from_start(&self) -> i32 {
-            let paths = self.search_for_paths();
+            let paths = self.search_for_paths(&mut Recorder::disabled());
 
             paths.iter().map(|p| p.length).max().unwrap()
         }
 
+        /// Record a frame per level of the breadth first search, visited pipes in red and the front in yellow.
+        pub fn record_search(&self, recorder: &mut Recorder) {
+            self.search_for_paths(recorder);
+        }
+
+        /// Record a frame per row of the scanline, the loop in red and enclosed tiles found so far in green.
+        pub fn record_enclosure(&self, recorder: &mut Recorder) {
+            let pipe_loop = self.find_loop();
+            self.enclosed_tiles(&pipe_loop, recorder);
+        }
+
         pub fn count_enclosed_area(&self) -> i32 {
-            let paths = self.search_for_paths();
-            // Can't be bothered to look for loops. Maybe it's the last remaining (ie longest) path?
-            if paths.len() > 1 {
-                panic!("I'm guessing theres one longest path");
+            let pipe_loop = self.find_loop();
+
+            let by_pick = self.count_enclosed_area_by_pick(&pipe_loop);
+            let by_scanline = self
+                .enclosed_tiles(&pipe_loop, &mut Recorder::disabled())
+                .len() as i32;
+            if by_pick != by_scanline {
+                panic!(
+                    "Shoelace/Pick found {} enclosed tiles but scanline found {}",
+                    by_pick, by_scanline
+                );
             }
-            // nope, doesn't work
 
-            let visited = &paths.first().unwrap().visited;
+            by_pick
+        }
 
-            let mut fill_stack: Vec<Coordinate> = vec![];
-            let mut filled: HashSet<Coordinate> = HashSet::new();
-            self.insert_unvisited_edges(&visited, &mut fill_stack);
+        /// Walk from the start around the pipes that connect back to it, returning the closed loop.
+        pub fn find_loop(&self) -> Loop {
+            let start = self
+                .pipes
+                .iter()
+                .find(|(_, p)| **p == Pipe::Start)
+                .map(|(c, _)| c.clone())
+                .unwrap();
 
-            // flood fill
-            let area: i32 = self.area();
-            let mut counter = 0;
-            loop {
-                let node = match fill_stack.pop() {
-                    Some(n) => n,
-                    None => break,
+            for direction in directions(&Pipe::Start) {
+                let Some(tiles) = self.walk_loop(&start, &direction) else {
+                    continue;
                 };
 
-                let should_fill =
-                    self.in_bounds(&node) && !visited.contains(&node) && !filled.contains(&node);
-                if !should_fill {
-                    continue;
-                }
+                let last = tiles.last().unwrap();
+                let back = Coordinate {
+                    x: last.x - start.x,
+                    y: last.y - start.y,
+                };
+                let start_pipe = pipe_between(&direction, &back).expect("Can't infer start pipe");
 
-                filled.insert(node.clone());
+                return Loop { tiles, start_pipe };
+            }
 
-                let next = [
-                    &node + &Coordinate::up(),
-                    &node + &Coordinate::right(),
-                    &node + &Coordinate::down(),
-                    &node + &Coordinate::left(),
-                ];
+            panic!("Can't find loop through start");
+        }
 
-                fill_stack.extend(next);
+        fn walk_loop(&self, start: &Coordinate, direction: &Coordinate) -> Option<Vec<Coordinate>> {
+            let mut tiles = vec![start.clone()];
+            let mut previous = start.clone();
+            let mut current = start + direction;
 
-                counter += 1;
-                if counter > area {
-                    panic!("{:?}", fill_stack);
+            while current != *start {
+                let pipe = self.pipes.get(&current)?;
+                let exits = directions(pipe);
+                if !exits.iter().any(|d| &current + d == previous) {
+                    return None;
                 }
-            }
 
-            let filled_count = filled.iter().count() as i32;
-            let visited_count = visited.iter().count() as i32;
+                let next = exits
+                    .iter()
+                    .map(|d| &current + d)
+                    .find(|c| *c != previous)?;
 
-            let enclosed_count = self.area() - filled_count - visited_count;
+                tiles.push(current.clone());
+                previous = current;
+                current = next;
+            }
 
-            self.display(&visited, &filled);
+            if tiles.len() < 4 {
+                return None;
+            }
 
-            enclosed_count
+            Some(tiles)
         }
 
-        fn area(&self) -> i32 {
-            self.width * self.height
+        fn count_enclosed_area_by_pick(&self, pipe_loop: &Loop) -> i32 {
+            // Shoelace formula gives twice the area of the polygon through the tile centres
+            let tiles = &pipe_loop.tiles;
+            let twice_area: i32 = tiles
+                .iter()
+                .zip(tiles.iter().cycle().skip(1))
+                .map(|(a, b)| a.x * b.y - b.x * a.y)
+                .sum::<i32>()
+                .abs();
+
+            // Pick's theorem: A = i + b/2 - 1
+            let boundary = tiles.len() as i32;
+            (twice_area - boundary + 2) / 2
         }
 
-        fn in_bounds(&self, coordinate: &Coordinate) -> bool {
-            coordinate.x >= 0
-                && coordinate.y >= 0
-                && coordinate.x < self.width
-                && coordinate.y < self.height
-        }
+        fn enclosed_tiles(&self, pipe_loop: &Loop, recorder: &mut Recorder) -> HashSet<Coordinate> {
+            let tiles: HashSet<&Coordinate> = pipe_loop.tiles.iter().collect();
+            let mut enclosed: HashSet<Coordinate> = HashSet::new();
 
-        pub fn display(&self, visited: &HashSet<Coordinate>, filled: &HashSet<Coordinate>) {
+            // Even-odd rule, crossing the loop whenever a tile on it connects north
             for y in 0..self.height {
+                let mut inside = false;
                 for x in 0..self.width {
                     let coord = Coordinate { x, y };
 
-                    let str = match self.pipes.get(&coord) {
-                        Some(p) => display_pipe(p),
-                        None => String::from("."),
+                    if !tiles.contains(&coord) {
+                        if inside {
+                            enclosed.insert(coord);
+                        }
+                        continue;
+                    }
+
+                    let pipe = match self.pipes.get(&coord) {
+                        Some(Pipe::Start) => &pipe_loop.start_pipe,
+                        Some(p) => p,
+                        None => continue,
                     };
 
-                    if visited.contains(&coord) {
-                        print!("{}", str.red());
-                    } else if filled.contains(&coord) {
-                        print!("{}", str.green());
-                    } else {
-                        print!("{}", str.white());
+                    if directions(pipe).contains(&Coordinate::up()) {
+                        inside = !inside;
                     }
-                    print!(" ");
                 }
-                println!();
+
+                recorder.record(|| {
+                    Scene::new(self.glyphs())
+                        .with_layer(Layer::new(
+                            "loop",
+                            Colour::Red,
+                            to_cells(tiles.iter().copied()),
+                        ))
+                        .with_layer(Layer::new(
+                            "enclosed",
+                            Colour::Green,
+                            to_cells(enclosed.iter()),
+                        ))
+                });
             }
+
+            enclosed
         }
 
-        fn insert_unvisited_edges(
-            &self,
-            visited: &HashSet<Coordinate>,
-            fill_stack: &mut Vec<Coordinate>,
-        ) {
-            // top and bottom
-            for y in [0, self.height - 1] {
-                for x in 0..self.width {
-                    let c = Coordinate { x, y };
-                    match visited.get(&c) {
-                        Some(_) => (),
-                        None => fill_stack.push(c),
-                    }
-                }
-            }
+        /// Render the pipes with the loop in red and the tiles it encloses in green.
+        pub fn scene(&self) -> Scene {
+            let pipe_loop = self.find_loop();
+            let enclosed = self.enclosed_tiles(&pipe_loop, &mut Recorder::disabled());
+
+            Scene::new(self.glyphs())
+                .with_layer(Layer::new(
+                    "loop",
+                    Colour::Red,
+                    to_cells(pipe_loop.tiles.iter()),
+                ))
+                .with_layer(Layer::new(
+                    "enclosed",
+                    Colour::Green,
+                    to_cells(enclosed.iter()),
+                ))
+        }
 
-            // remaining sides
-            for y in 1..(self.height - 1) {
-                for x in [0, self.width - 1] {
-                    let c = Coordinate { x, y };
-                    match visited.get(&c) {
-                        Some(_) => (),
-                        None => fill_stack.push(c),
-                    }
-                }
-            }
+        fn glyphs(&self) -> Vec<Vec<char>> {
+            (0..self.height)
+                .map(|y| {
+                    (0..self.width)
+                        .map(|x| match self.pipes.get(&Coordinate { x, y }) {
+                            Some(p) => display_pipe(p),
+                            None => '.',
+                        })
+                        .collect()
+                })
+                .collect()
         }
 
-        fn search_for_paths(&self) -> Vec<Path> {
+        fn search_for_paths(&self, recorder: &mut Recorder) -> Vec<Path> {
             let start = self
                 .pipes
                 .iter()
-                .find(|(c, p)| **p == Pipe::Start)
+                .find(|(_c, p)| **p == Pipe::Start)
                 .map(|(c, p)| (c.clone(), p.clone()))
                 .unwrap();
 
@@ -195,6 +272,16 @@ mod pipe_maze {
                     break;
                 }
 
+                recorder.record(|| {
+                    Scene::new(self.glyphs())
+                        .with_layer(Layer::new("visited", Colour::Red, to_cells(visited.iter())))
+                        .with_layer(Layer::new(
+                            "front",
+                            Colour::Yellow,
+                            to_cells(next_level.iter().map(|p| &p.end.0)),
+                        ))
+                });
+
                 search_level = next_level;
             }
 
@@ -209,19 +296,18 @@ mod pipe_maze {
             let directions_from_current = directions(&path.end.1);
             let accessable_coordinates = directions_from_current.iter().filter_map(|direction| {
                 let accessable: Coordinate = direction + &path.end.0;
-                match self.pipes.get(&accessable) {
-                    Some(p) => Some((accessable.clone(), p.clone())),
-                    None => None,
-                }
+                self.pipes
+                    .get(&accessable)
+                    .map(|p| (accessable.clone(), p.clone()))
             });
 
             let next_coordinates = accessable_coordinates
                 .filter(|(c, p)| {
-                    let directions_from_adjacent = directions(&p); // todo double work. Actually more than double. Optimize by storing against enum somehow?
+                    let directions_from_adjacent = directions(p); // todo double work. Actually more than double. Optimize by storing against enum somehow?
                     let is_connected = directions_from_adjacent.iter().any(|d| c + d == path.end.0);
                     is_connected
                 })
-                .filter(|(c, _)| !visited.contains(&c))
+                .filter(|(c, _)| !visited.contains(c))
                 .collect::<Vec<(Coordinate, Pipe)>>();
 
             let next_paths: Vec<Path> = next_coordinates
@@ -284,18 +370,39 @@ mod pipe_maze {
         Start,
     }
 
-    fn display_pipe(pipe: &Pipe) -> String {
-        let s = match pipe {
-            Pipe::Vertical => "↑",
-            Pipe::Horizontal => "→",
-            Pipe::NorthEast => "↳",
-            Pipe::NorthWest => "↲",
-            Pipe::SouthWest => "↰",
-            Pipe::SouthEast => "↱",
-            Pipe::Start => "S",
-        };
-
-        String::from(s)
+    fn display_pipe(pipe: &Pipe) -> char {
+        match pipe {
+            Pipe::Vertical => '↑',
+            Pipe::Horizontal => '→',
+            Pipe::NorthEast => '↳',
+            Pipe::NorthWest => '↲',
+            Pipe::SouthWest => '↰',
+            Pipe::SouthEast => '↱',
+            Pipe::Start => 'S',
+        }
+    }
+
+    fn to_cells<'a, I>(coordinates: I) -> Vec<(usize, usize)>
+    where
+        I: Iterator<Item = &'a Coordinate>,
+    {
+        coordinates.map(|c| (c.x as usize, c.y as usize)).collect()
+    }
+
+    fn pipe_between(first: &Coordinate, second: &Coordinate) -> Option<Pipe> {
+        [
+            Pipe::Vertical,
+            Pipe::Horizontal,
+            Pipe::NorthEast,
+            Pipe::NorthWest,
+            Pipe::SouthWest,
+            Pipe::SouthEast,
+        ]
+        .into_iter()
+        .find(|p| {
+            let exits = directions(p);
+            exits.contains(first) && exits.contains(second)
+        })
     }
 
     fn directions(pipe: &Pipe) -> Vec<Coordinate> {
@@ -324,17 +431,14 @@ mod pipe_maze {
                 None => width = Some(line.len()),
             }
             for (x, char) in line.chars().enumerate() {
-                match parse_pipe(char) {
-                    Some(p) => {
-                        hash_map.insert(
-                            Coordinate {
-                                x: x as i32,
-                                y: y as i32,
-                            },
-                            p,
-                        );
-                    }
-                    None => (),
+                if let Some(p) = parse_pipe(char) {
+                    hash_map.insert(
+                        Coordinate {
+                            x: x as i32,
+                            y: y as i32,
+                        },
+                        p,
+                    );
                 };
             }
         }
@@ -363,7 +467,8 @@ mod pipe_maze {
 
 #[cfg(test)]
 mod tests {
-    use crate::pipe_maze::parse;
+    use crate::pipe_maze::{parse, Pipe};
+    use common::render::Recorder;
 
     #[test]
     fn can_find_distance_to_pipe_furthest_from_start() {
@@ -447,4 +552,91 @@ mod tests {
 
         assert_eq!(count, 10);
     }
+
+    #[test]
+    fn can_infer_pipe_under_start() {
+        #[rustfmt::skip]
+        let square_loop = vec![
+            "-L|F7",
+            "7S-7|",
+            "L|7||",
+            "-L-J|",
+            "L|-JF"
+        ];
+
+        let maze = parse(&square_loop);
+
+        let pipe_loop = maze.find_loop();
+
+        assert_eq!(pipe_loop.start_pipe, Pipe::SouthEast);
+        assert_eq!(pipe_loop.tiles.len(), 8);
+    }
+
+    #[test]
+    fn can_find_enclosed_area_squeezing_between_pipes() {
+        let example = vec![
+            "..........",
+            ".S------7.",
+            ".|F----7|.",
+            ".||....||.",
+            ".||....||.",
+            ".|L-7F-J|.",
+            ".|..||..|.",
+            ".L--JL--J.",
+            "..........",
+        ];
+
+        let maze = parse(&example);
+
+        let count = maze.count_enclosed_area();
+
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn can_render_loop_and_enclosed_area() {
+        #[rustfmt::skip]
+        let square_loop = vec![
+            "S-7",
+            "|.|",
+            "L-J",
+        ];
+
+        let maze = parse(&square_loop);
+
+        let ansi = maze.scene().to_ansi();
+
+        assert_eq!(
+            ansi,
+            [
+                "\x1b[31mS\x1b[0m\x1b[31m→\x1b[0m\x1b[31m↰\x1b[0m\n",
+                "\x1b[31m↑\x1b[0m\x1b[32m.\x1b[0m\x1b[31m↑\x1b[0m\n",
+                "\x1b[31m↳\x1b[0m\x1b[31m→\x1b[0m\x1b[31m↲\x1b[0m\n",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn can_record_search_and_enclosure() {
+        #[rustfmt::skip]
+        let square_loop = vec![
+            ".....",
+            ".S-7.",
+            ".|.|.",
+            ".L-J.",
+            "....."
+        ];
+
+        let maze = parse(&square_loop);
+        let mut recorder = Recorder::new();
+
+        maze.record_search(&mut recorder);
+        maze.record_enclosure(&mut recorder);
+
+        // one frame per search level, then one per scanline row
+        assert_eq!(recorder.frames().len(), 4 + 5);
+        assert!(recorder.frames()[3].to_ansi().contains("\x1b[33m↲\x1b[0m"));
+        assert!(recorder.frames()[8].to_ansi().contains("\x1b[32m.\x1b[0m"));
+    }
 }
@@ -1,5 +1,11 @@
-use crate::astronomy::{image::Image, sum_distances_between_galaxy_pairs};
+use crate::astronomy::{
+    farthest_pair, find_pairs,
+    image::{Expansion, Image},
+    nearest_neighbours, sum_distances_between_galaxy_pairs,
+};
+use crate::geometry::Metric;
 use common::file;
+use std::env;
 
 fn main() {
     let file = String::from("./input.txt");
@@ -8,49 +14,299 @@ fn main() {
 
     let image = Image::new(&v);
 
-    println!("part 1: {}", sum_distances_between_galaxy_pairs(&image, 1));
+    let sum = |factor| {
+        sum_distances_between_galaxy_pairs(&image, &Expansion::uniform(factor), Metric::Manhattan)
+            .unwrap()
+    };
+    println!("part 1: {}", sum(2));
+    println!("part 2: {}", sum(1_000_000));
+
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|a| a.as_str()) {
+        Some("neighbours") => print_neighbours(&image, metric(args.get(2))),
+        Some("expand") => print_expanded_sum(&image, &args[2..]),
+        Some(path) => image.scene().write(path).unwrap(),
+        None => (),
+    }
+}
+
+fn metric(name: Option<&String>) -> Metric {
+    match name.map(|n| n.as_str()) {
+        None | Some("manhattan") => Metric::Manhattan,
+        Some("chebyshev") => Metric::Chebyshev,
+        Some("squared") => Metric::SquaredEuclidean,
+        Some(other) => panic!("unknown metric '{}'", other),
+    }
+}
+
+/// `expand <rows> <columns> [sparse] [metric]`, where sparse also grows the rows and columns
+/// with a single galaxy.
+fn print_expanded_sum(image: &Image, args: &[String]) {
+    let factor = |i: usize| args.get(i).and_then(|a| a.parse().ok());
+    let (Some(rows), Some(columns)) = (factor(0), factor(1)) else {
+        println!("usage: expand <rows> <columns> [sparse] [metric]");
+        return;
+    };
+
+    let mut expansion = Expansion::new(rows, columns);
+    let mut rest = &args[2..];
+    if rest.first().map(|a| a.as_str()) == Some("sparse") {
+        expansion = expansion.with_rule(|line| line.iter().filter(|&&p| p == '#').count() <= 1);
+        rest = &rest[1..];
+    }
+
+    match sum_distances_between_galaxy_pairs(image, &expansion, metric(rest.first())) {
+        Ok(sum) => println!("sum of distances: {}", sum),
+        Err(e) => println!("Failed {}", e.get_reason()),
+    }
+}
+
+/// Galaxies are numbered from 1 in reading order, as in the puzzle. Always uses part one's
+/// expansion, where each empty row and column doubles.
+fn print_neighbours(image: &Image, metric: Metric) {
+    let galaxies = image.galaxies(&Expansion::uniform(2)).unwrap();
+
     println!(
-        "part 2: {}",
-        sum_distances_between_galaxy_pairs(&image, 1_000_000)
+        "{} galaxies, {} pairs",
+        galaxies.len(),
+        find_pairs(&galaxies).count()
     );
+    if let Some(pair) = farthest_pair(&galaxies, metric) {
+        println!(
+            "farthest: {} and {}, {} apart",
+            pair.left + 1,
+            pair.right + 1,
+            pair.distance
+        );
+    }
+
+    for (i, nearest) in nearest_neighbours(&galaxies, metric).iter().enumerate() {
+        match nearest {
+            Some(pair) => println!(
+                "{}: nearest {}, {} away",
+                i + 1,
+                pair.right + 1,
+                pair.distance
+            ),
+            None => println!("{}: alone", i + 1),
+        }
+    }
 }
 
 mod astronomy {
-    use crate::geometry::calculate_path_distance;
+    use common::core::{Error, Result};
 
-    use self::image::Image;
+    use crate::geometry::{Coordinate, Metric};
 
-    pub fn sum_distances_between_galaxy_pairs(image: &Image, expansion_factor: i32) -> i64 {
-        let galaxies = image.galaxies(expansion_factor);
+    use self::image::{Expansion, Image};
 
-        let pairs = find_pairs(&galaxies);
+    pub fn sum_distances_between_galaxy_pairs(
+        image: &Image,
+        expansion: &Expansion,
+        metric: Metric,
+    ) -> Result<i128> {
+        sum_of_distances(&image.galaxies(expansion)?, metric)
+            .ok_or_else(|| Error::new("sum of distances overflows i128"))
+    }
+
+    /// The sum of the distance between every pair, without visiting the pairs. `None` if it
+    /// doesn't fit in an i128.
+    pub fn sum_of_distances(galaxies: &[Coordinate], metric: Metric) -> Option<i128> {
+        let axis = |key: fn(&Coordinate) -> i128| galaxies.iter().map(key).collect::<Vec<i128>>();
+
+        match metric {
+            Metric::Manhattan => sum_along_axis(axis(|g| g.x as i128))?
+                .checked_add(sum_along_axis(axis(|g| g.y as i128))?),
+            // max(|dx|, |dy|) is half of |du| + |dv| for u = x + y and v = x - y
+            Metric::Chebyshev => Some(
+                sum_along_axis(axis(|g| g.x as i128 + g.y as i128))?
+                    .checked_add(sum_along_axis(axis(|g| g.x as i128 - g.y as i128))?)?
+                    / 2,
+            ),
+            Metric::SquaredEuclidean => sum_of_squares_along_axis(axis(|g| g.x as i128))?
+                .checked_add(sum_of_squares_along_axis(axis(|g| g.y as i128))?),
+        }
+    }
+
+    /// Along a sorted axis the i-th value is subtracted from by the i before it.
+    fn sum_along_axis(mut values: Vec<i128>) -> Option<i128> {
+        values.sort_unstable();
 
-        let distances = pairs.iter().map(|p| calculate_path_distance(p.0, p.1));
+        let mut sum: i128 = 0;
+        let mut preceding: i128 = 0;
+        for (i, &value) in values.iter().enumerate() {
+            let gaps = value.checked_mul(i as i128)?.checked_sub(preceding)?;
+            sum = sum.checked_add(gaps)?;
+            preceding = preceding.checked_add(value)?;
+        }
 
-        distances.sum()
+        Some(sum)
     }
 
-    pub fn find_pairs<T>(collection: &[T]) -> Vec<(&T, &T)> {
-        let mut pairs: Vec<(&T, &T)> = vec![];
-        for left in collection.iter().enumerate() {
-            for right in collection.iter().enumerate() {
-                if left.0 < right.0 {
-                    pairs.push((&left.1, &right.1))
-                }
+    /// The sum over pairs of (a - b)^2 is n * sum(a^2) - sum(a)^2.
+    fn sum_of_squares_along_axis(values: Vec<i128>) -> Option<i128> {
+        let mut sum: i128 = 0;
+        let mut squares: i128 = 0;
+        for value in values.iter() {
+            sum = sum.checked_add(*value)?;
+            squares = squares.checked_add(value.checked_mul(*value)?)?;
+        }
+
+        (values.len() as i128)
+            .checked_mul(squares)?
+            .checked_sub(sum.checked_mul(sum)?)
+    }
+
+    /// Two galaxies, by their index in the list they came from, and how far apart they are.
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub struct Pair {
+        pub left: usize,
+        pub right: usize,
+        pub distance: i128,
+    }
+
+    /// The closest other galaxy to each galaxy, or `None` if it's the only one. Sweeps out
+    /// from each galaxy in x order and stops once the x gap alone is further than the best.
+    pub fn nearest_neighbours(galaxies: &[Coordinate], metric: Metric) -> Vec<Option<Pair>> {
+        let mut by_x: Vec<usize> = (0..galaxies.len()).collect();
+        by_x.sort_unstable_by_key(|&i| galaxies[i].x);
+
+        let mut nearest: Vec<Option<Pair>> = vec![None; galaxies.len()];
+        for (position, &left) in by_x.iter().enumerate() {
+            let after = by_x[position + 1..].iter();
+            let before = by_x[..position].iter().rev();
+
+            let best = closest_in_sweep(galaxies, metric, left, after, None);
+            nearest[left] = closest_in_sweep(galaxies, metric, left, before, best);
+        }
+
+        nearest
+    }
+
+    fn closest_in_sweep<'a>(
+        galaxies: &[Coordinate],
+        metric: Metric,
+        left: usize,
+        sweep: impl Iterator<Item = &'a usize>,
+        mut best: Option<Pair>,
+    ) -> Option<Pair> {
+        let galaxy = &galaxies[left];
+        let on_x_axis = |g: &Coordinate| Coordinate { x: g.x, y: 0 };
+
+        for &right in sweep {
+            let best_distance = best.map_or(i128::MAX, |p| p.distance);
+            // every metric here is at least as far as the x gap on its own
+            if metric.distance(&on_x_axis(galaxy), &on_x_axis(&galaxies[right])) >= best_distance {
+                break;
             }
+
+            let distance = metric.distance(galaxy, &galaxies[right]);
+            if distance < best_distance {
+                best = Some(Pair {
+                    left,
+                    right,
+                    distance,
+                });
+            }
+        }
+
+        best
+    }
+
+    /// The two galaxies furthest apart. Chebyshev distance only depends on the extremes of x
+    /// and y. Turning the grid 45 degrees makes Manhattan distance the Chebyshev distance of
+    /// `u = x + y` and `v = x - y`. Squared Euclidean distance checks every pair.
+    pub fn farthest_pair(galaxies: &[Coordinate], metric: Metric) -> Option<Pair> {
+        let extremes = |key: fn(&Coordinate) -> i128| {
+            let indices = 0..galaxies.len();
+            let lowest = indices.clone().min_by_key(|&i| key(&galaxies[i]))?;
+            let highest = indices.max_by_key(|&i| key(&galaxies[i]))?;
+
+            Some(Pair {
+                left: lowest.min(highest),
+                right: lowest.max(highest),
+                distance: key(&galaxies[highest]) - key(&galaxies[lowest]),
+            })
+        };
+
+        let (first, second) = match metric {
+            Metric::Manhattan => (
+                extremes(|g| g.x as i128 + g.y as i128)?,
+                extremes(|g| g.x as i128 - g.y as i128)?,
+            ),
+            Metric::Chebyshev => (extremes(|g| g.x as i128)?, extremes(|g| g.y as i128)?),
+            Metric::SquaredEuclidean => {
+                let indices: Vec<usize> = (0..galaxies.len()).collect();
+                return find_pairs(&indices)
+                    .map(|(&left, &right)| Pair {
+                        left,
+                        right,
+                        distance: metric.distance(&galaxies[left], &galaxies[right]),
+                    })
+                    .max_by_key(|p| p.distance);
+            }
+        };
+
+        match galaxies.len() {
+            0 | 1 => None,
+            _ if first.distance >= second.distance => Some(first),
+            _ => Some(second),
         }
+    }
 
-        pairs
+    /// Every pair once, left before right, made as they're asked for.
+    pub fn find_pairs<T>(collection: &[T]) -> impl Iterator<Item = (&T, &T)> {
+        collection
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, left)| collection[i + 1..].iter().map(move |right| (left, right)))
     }
 
     pub mod image {
-        use std::collections::HashSet;
+        use common::core::{Error, Result};
+        use common::render::{Colour, Layer, Scene};
 
         use crate::geometry::Coordinate;
 
         const GALAXY: char = '#';
         //const EMPTY_SPACE: char = '.';
 
+        /// The puzzle's rule: a row or column expands when it has no galaxies.
+        pub fn no_galaxy(line: &[char]) -> bool {
+            !line.contains(&GALAXY)
+        }
+
+        /// Whether a row or column expands, given its pixels.
+        pub type Rule = dyn Fn(&[char]) -> bool;
+
+        /// How big each row and column picked out by the rule becomes. A factor of 1 leaves
+        /// them alone and 0 removes them.
+        pub struct Expansion {
+            pub rows: u64,
+            pub columns: u64,
+            rule: Box<Rule>,
+        }
+
+        impl Expansion {
+            pub fn new(rows: u64, columns: u64) -> Self {
+                Expansion {
+                    rows,
+                    columns,
+                    rule: Box::new(no_galaxy),
+                }
+            }
+
+            pub fn uniform(factor: u64) -> Self {
+                Self::new(factor, factor)
+            }
+
+            /// Choose which rows and columns expand, given their pixels.
+            pub fn with_rule(mut self, rule: impl Fn(&[char]) -> bool + 'static) -> Self {
+                self.rule = Box::new(rule);
+                self
+            }
+        }
+
         #[derive(Debug)]
         pub struct Image {
             pixels: Vec<Vec<char>>,
@@ -63,54 +319,82 @@ mod astronomy {
                 }
             }
 
-            pub fn galaxies(&self, expansion_factor: i32) -> Vec<Coordinate> {
-                if expansion_factor < 1 {
-                    panic!("expansion factor must be 1 or more")
-                }
+            /// Galaxies in an expanding row or column sit at the start of it.
+            pub fn galaxies(&self, expansion: &Expansion) -> Result<Vec<Coordinate>> {
+                let (rows, columns) = self.expanding(&expansion.rule);
+                let ys = expanded_positions(&rows, expansion.rows)?;
+                let xs = expanded_positions(&columns, expansion.columns)?;
 
-                let mut non_empty_rows = HashSet::<usize>::new();
-                let mut non_empty_columns = HashSet::<usize>::new();
+                let mut galaxies = Vec::<Coordinate>::new();
                 for (y, line) in self.pixels.iter().enumerate() {
                     for (x, pixel) in line.iter().enumerate() {
                         if *pixel == GALAXY {
-                            non_empty_rows.insert(y);
-                            non_empty_columns.insert(x);
+                            galaxies.push(Coordinate { x: xs[x], y: ys[y] })
                         }
                     }
                 }
 
-                let mut galaxies = Vec::<Coordinate>::new();
-
-                let mut y_expanded: i64 = 0;
-                for (y, line) in self.pixels.iter().enumerate() {
-                    if !non_empty_rows.contains(&y) {
-                        y_expanded += expansion_factor as i64;
-                        continue;
-                    }
+                Ok(galaxies)
+            }
 
-                    let mut x_expanded: i64 = 0;
-                    for (x, char) in line.iter().enumerate() {
-                        if !non_empty_columns.contains(&x) {
-                            x_expanded += expansion_factor as i64;
-                            continue;
-                        }
+            /// Which rows and which columns the rule picks out.
+            fn expanding(&self, rule: impl Fn(&[char]) -> bool) -> (Vec<bool>, Vec<bool>) {
+                let width = self.pixels.iter().map(|l| l.len()).max().unwrap_or(0);
+
+                let rows = self.pixels.iter().map(|l| rule(l)).collect();
+                let columns = (0..width)
+                    .map(|x| {
+                        let column: Vec<char> = self
+                            .pixels
+                            .iter()
+                            .filter_map(|l| l.get(x))
+                            .copied()
+                            .collect();
+                        rule(&column)
+                    })
+                    .collect();
+
+                (rows, columns)
+            }
 
-                        if *char == GALAXY {
-                            galaxies.push(Coordinate {
-                                x: x_expanded,
-                                y: y_expanded,
-                            })
+            /// Render the unexpanded image, galaxies in yellow and the rows and columns that will expand in blue.
+            pub fn scene(&self) -> Scene {
+                let (rows, columns) = self.expanding(no_galaxy);
+
+                let mut galaxies: Vec<(usize, usize)> = vec![];
+                let mut expanding: Vec<(usize, usize)> = vec![];
+                for (y, row) in rows.iter().enumerate() {
+                    for (x, column) in columns.iter().enumerate() {
+                        if self.pixels[y].get(x) == Some(&GALAXY) {
+                            galaxies.push((x, y));
+                        } else if *row || *column {
+                            expanding.push((x, y));
                         }
-
-                        x_expanded += 1;
                     }
-
-                    y_expanded += 1;
                 }
 
-                galaxies
+                Scene::new(self.pixels.clone())
+                    .with_layer(Layer::new("expanding", Colour::Blue, expanding))
+                    .with_layer(Layer::new("galaxies", Colour::Yellow, galaxies))
             }
         }
+
+        /// Where each row (or column) starts once the expanding ones have grown.
+        fn expanded_positions(expanding: &[bool], factor: u64) -> Result<Vec<i64>> {
+            let overflow = || Error::new(&format!("expanding by {} overflows i64", factor));
+            let factor = i64::try_from(factor).map_err(|_| overflow())?;
+
+            let mut positions = Vec::with_capacity(expanding.len());
+            let mut position: i64 = 0;
+            for &expands in expanding {
+                positions.push(position);
+                position = position
+                    .checked_add(if expands { factor } else { 1 })
+                    .ok_or_else(overflow)?;
+            }
+
+            Ok(positions)
+        }
     }
 }
 
@@ -122,25 +406,45 @@ mod geometry {
         pub y: i64,
     }
 
-    pub fn calculate_path_distance(left: &Coordinate, right: &Coordinate) -> i64 {
-        let x_distance = i64::abs(right.x - left.x);
-        let y_distance = i64::abs(right.y - left.y);
-
-        // let non_diagonal_component = i64::abs(x_distance - y_distance);
-        // let diagonal_component = i64::max(x_distance, y_distance) - non_diagonal_component;
-
-        // non_diagonal_component + diagonal_component
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub enum Metric {
+        /// Steps up, down, left and right.
+        Manhattan,
+        /// Diagonal steps count as one.
+        Chebyshev,
+        /// Straight-line distance squared, which keeps it exact.
+        SquaredEuclidean,
+    }
 
-        x_distance + y_distance
+    impl Metric {
+        /// Exact for any galaxies in an image, as they are never at negative coordinates.
+        /// Squared Euclidean distance stops at `i128::MAX` for points further apart than that.
+        pub fn distance(&self, left: &Coordinate, right: &Coordinate) -> i128 {
+            let x_distance = i128::abs(right.x as i128 - left.x as i128);
+            let y_distance = i128::abs(right.y as i128 - left.y as i128);
+
+            match self {
+                Metric::Manhattan => x_distance + y_distance,
+                Metric::Chebyshev => x_distance.max(y_distance),
+                Metric::SquaredEuclidean => x_distance
+                    .saturating_mul(x_distance)
+                    .saturating_add(y_distance.saturating_mul(y_distance)),
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        astronomy::{find_pairs, image::Image, sum_distances_between_galaxy_pairs},
-        geometry::Coordinate,
+        astronomy::{
+            farthest_pair, find_pairs,
+            image::{Expansion, Image},
+            nearest_neighbours, sum_distances_between_galaxy_pairs, sum_of_distances,
+        },
+        geometry::{Coordinate, Metric},
     };
+    use common::random::Random;
 
     #[rustfmt::skip]
     const IMAGE: &[&str] = &[
@@ -161,17 +465,19 @@ mod tests {
     #[test]
     fn part_1_works() {
         let image = Image::new(IMAGE);
-        let answer = sum_distances_between_galaxy_pairs(&image, 2);
+        let answer =
+            sum_distances_between_galaxy_pairs(&image, &Expansion::uniform(2), Metric::Manhattan);
 
-        assert_eq!(answer, 374);
+        assert_eq!(answer.unwrap(), 374);
     }
 
     #[test]
     fn part_2_works() {
         let image = Image::new(IMAGE);
-        let answer = sum_distances_between_galaxy_pairs(&image, 10);
+        let answer =
+            sum_distances_between_galaxy_pairs(&image, &Expansion::uniform(10), Metric::Manhattan);
 
-        assert_eq!(answer, 1030);
+        assert_eq!(answer.unwrap(), 1030);
     }
 
     #[test]
@@ -184,7 +490,7 @@ mod tests {
             Coordinate { y: 2, x: 0 },
         ];
 
-        let galaxies = image.galaxies(1);
+        let galaxies = image.galaxies(&Expansion::uniform(1)).unwrap();
 
         assert_eq!(galaxies, expected);
     }
@@ -214,7 +520,7 @@ mod tests {
             Coordinate { y: 3 + 9, x: 0 },
         ];
 
-        let galaxies = image.galaxies(10);
+        let galaxies = image.galaxies(&Expansion::uniform(10)).unwrap();
 
         assert_eq!(galaxies, expected);
     }
@@ -226,12 +532,30 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn can_render_galaxies_and_expansion() {
+        let image_lines = vec!["#..", "...", "..#"];
+        let image = Image::new(&image_lines);
+
+        let ansi = image.scene().to_ansi();
+
+        assert_eq!(
+            ansi,
+            [
+                "\x1b[33m#\x1b[0m\x1b[34m.\x1b[0m.\n",
+                "\x1b[34m.\x1b[0m\x1b[34m.\x1b[0m\x1b[34m.\x1b[0m\n",
+                ".\x1b[34m.\x1b[0m\x1b[33m#\x1b[0m\n",
+            ]
+            .concat()
+        );
+    }
+
     #[test]
     fn can_get_pairs() {
         let items = vec![5, 6, 7, 8];
-        let expected = vec![(5, 6), (5, 7), (5, 8), (6, 7), (6, 8), (7, 8)];
+        let expected = [(5, 6), (5, 7), (5, 8), (6, 7), (6, 8), (7, 8)];
 
-        let pairs = find_pairs(&items);
+        let pairs: Vec<(&i32, &i32)> = find_pairs(&items).collect();
 
         assert_eq!(
             pairs,
@@ -241,11 +565,179 @@ mod tests {
                 .collect::<Vec<(&i32, &i32)>>()
         );
     }
+
+    /// Scattered galaxies from a fixed seed, with some sharing rows and columns.
+    fn random_galaxies(seed: u64, count: usize) -> Vec<Coordinate> {
+        let mut random = Random::new(seed);
+        let mut next = || random.next(200) as i64 - 100;
+
+        (0..count)
+            .map(|_| Coordinate {
+                x: next(),
+                y: next(),
+            })
+            .collect()
+    }
+
+    const METRICS: [Metric; 3] = [
+        Metric::Manhattan,
+        Metric::Chebyshev,
+        Metric::SquaredEuclidean,
+    ];
+
+    #[test]
+    fn sum_of_distances_matches_every_pair() {
+        for (seed, count) in [(1, 0), (2, 1), (3, 2), (4, 50), (5, 300)] {
+            let galaxies = random_galaxies(seed, count);
+
+            for metric in METRICS {
+                let expected: i128 = find_pairs(&galaxies)
+                    .map(|(l, r)| metric.distance(l, r))
+                    .sum();
+
+                assert_eq!(
+                    sum_of_distances(&galaxies, metric),
+                    Some(expected),
+                    "{:?}",
+                    metric
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_neighbours_match_every_pair() {
+        let galaxies = random_galaxies(2023, 200);
+
+        for metric in METRICS {
+            let nearest = nearest_neighbours(&galaxies, metric);
+
+            for (i, galaxy) in galaxies.iter().enumerate() {
+                let expected = (0..galaxies.len())
+                    .filter(|&j| j != i)
+                    .map(|j| metric.distance(galaxy, &galaxies[j]))
+                    .min();
+                assert_eq!(nearest[i].map(|p| p.distance), expected, "{:?}", metric);
+            }
+        }
+    }
+
+    #[test]
+    fn can_find_farthest_pair() {
+        let image = Image::new(IMAGE);
+        let galaxies = image.galaxies(&Expansion::uniform(2)).unwrap();
+
+        for metric in METRICS {
+            let farthest = farthest_pair(&galaxies, metric);
+
+            let expected = find_pairs(&galaxies)
+                .map(|(l, r)| metric.distance(l, r))
+                .max()
+                .unwrap();
+            assert_eq!(farthest.map(|p| p.distance), Some(expected), "{:?}", metric);
+            assert_eq!(farthest_pair(&galaxies[..1], metric), None);
+        }
+    }
+
+    #[test]
+    fn can_measure_squared_distances_past_i64() {
+        let image = Image::new(&["#..", "...", "..#"]);
+        let galaxies = image.galaxies(&Expansion::uniform(10_000_000_000)).unwrap();
+        let metric = Metric::SquaredEuclidean;
+        let expected = 2 * 10_000_000_001_i128 * 10_000_000_001;
+
+        assert_eq!(
+            nearest_neighbours(&galaxies, metric)[0].unwrap().distance,
+            expected
+        );
+        assert_eq!(farthest_pair(&galaxies, metric).unwrap().distance, expected);
+        assert_eq!(sum_of_distances(&galaxies, metric), Some(expected));
+    }
+
+    #[test]
+    fn can_turn_the_grid_past_i64() {
+        let image = Image::new(&["#..", "...", "..#"]);
+        let expansion = Expansion::uniform(1 << 62);
+        let galaxies = image.galaxies(&expansion).unwrap();
+        let side = (1 << 62) + 1;
+
+        assert_eq!(
+            sum_distances_between_galaxy_pairs(&image, &expansion, Metric::Chebyshev).unwrap(),
+            side
+        );
+        assert_eq!(
+            farthest_pair(&galaxies, Metric::Manhattan)
+                .unwrap()
+                .distance,
+            2 * side
+        );
+    }
+
+    #[test]
+    fn reports_sums_past_i128() {
+        let far = Coordinate {
+            x: i64::MAX,
+            y: i64::MAX,
+        };
+        let galaxies = vec![Coordinate { x: 0, y: 0 }, far.clone(), far.clone(), far];
+
+        assert_eq!(sum_of_distances(&galaxies, Metric::SquaredEuclidean), None);
+        assert_eq!(
+            farthest_pair(&galaxies, Metric::SquaredEuclidean)
+                .unwrap()
+                .distance,
+            2 * (i64::MAX as i128).pow(2)
+        );
+    }
+
+    #[test]
+    fn can_expand_rows_and_columns_separately() {
+        let image = Image::new(&["#..", "...", "..#"]);
+
+        let galaxies = image.galaxies(&Expansion::new(5, 0)).unwrap();
+
+        assert_eq!(
+            galaxies,
+            vec![Coordinate { x: 0, y: 0 }, Coordinate { x: 1, y: 6 }]
+        );
+    }
+
+    #[test]
+    fn can_expand_by_another_rule() {
+        let image = Image::new(&["#.#", "..#", "..."]);
+        let sparse = |line: &[char]| line.iter().filter(|&&p| p == '#').count() <= 1;
+
+        let galaxies = image
+            .galaxies(&Expansion::uniform(3).with_rule(sparse))
+            .unwrap();
+
+        // row 1 and column 0 have a galaxy but still grow
+        assert_eq!(
+            galaxies,
+            vec![
+                Coordinate { x: 0, y: 0 },
+                Coordinate { x: 6, y: 0 },
+                Coordinate { x: 6, y: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_expansion_past_i64() {
+        let image = Image::new(IMAGE);
+
+        let result = image.galaxies(&Expansion::uniform(u64::MAX));
+
+        assert_eq!(
+            result.unwrap_err().get_reason(),
+            &format!("expanding by {} overflows i64", u64::MAX)
+        );
+    }
 }
 
 #[cfg(test)]
 mod geometry_tests {
-    use crate::geometry::{calculate_path_distance, Coordinate};
+    use crate::geometry::{Coordinate, Metric};
     use test_case::test_case;
 
     #[test_case(Coordinate { x: 5, y: 7 }, Coordinate { x: 5, y: 7 }, 0; "itself")]
@@ -255,9 +747,19 @@ mod geometry_tests {
     #[test_case(Coordinate { x: 2, y: 0 }, Coordinate { x: 1, y: 0 }, 1; "1 left")]
     #[test_case(Coordinate { x: 0, y: 0 }, Coordinate { x: 1, y: 1 }, 2; "1 down and right")]
     #[test_case(Coordinate { x: 2, y: 2 }, Coordinate { x: 0, y: 0 }, 4; "2 up and left")]
-    fn can_get_distance_between_coordinates(left: Coordinate, right: Coordinate, expected: i64) {
-        let distance = calculate_path_distance(&left, &right);
+    fn can_get_distance_between_coordinates(left: Coordinate, right: Coordinate, expected: i128) {
+        let distance = Metric::Manhattan.distance(&left, &right);
 
         assert_eq!(distance, expected);
     }
+
+    #[test_case(Metric::Manhattan, 7)]
+    #[test_case(Metric::Chebyshev, 4)]
+    #[test_case(Metric::SquaredEuclidean, 25)]
+    fn can_measure_with_each_metric(metric: Metric, expected: i128) {
+        let left = Coordinate { x: 1, y: 5 };
+        let right = Coordinate { x: 4, y: 1 };
+
+        assert_eq!(metric.distance(&left, &right), expected);
+    }
 }
@@ -31,3 +31,321 @@ pub mod file {
         }
     }
 }
+
+pub mod render {
+    use crate::core::AdventError;
+    use std::{collections::HashSet, fs};
+
+    const PNG_BACKGROUND: [u8; 3] = [0, 0, 0];
+    const PNG_GLYPH: [u8; 3] = [80, 80, 80];
+
+    #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+    pub enum Colour {
+        Red,
+        Green,
+        Yellow,
+        Blue,
+        Magenta,
+        Cyan,
+        White,
+    }
+
+    impl Colour {
+        fn ansi_code(&self) -> u8 {
+            match self {
+                Colour::Red => 31,
+                Colour::Green => 32,
+                Colour::Yellow => 33,
+                Colour::Blue => 34,
+                Colour::Magenta => 35,
+                Colour::Cyan => 36,
+                Colour::White => 37,
+            }
+        }
+
+        fn rgb(&self) -> [u8; 3] {
+            match self {
+                Colour::Red => [220, 50, 47],
+                Colour::Green => [133, 153, 0],
+                Colour::Yellow => [181, 137, 0],
+                Colour::Blue => [38, 139, 210],
+                Colour::Magenta => [211, 54, 130],
+                Colour::Cyan => [42, 161, 152],
+                Colour::White => [238, 232, 213],
+            }
+        }
+
+        fn hex(&self) -> String {
+            let [r, g, b] = self.rgb();
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        }
+    }
+
+    /// A named set of cells to highlight in one colour.
+    #[derive(Debug)]
+    pub struct Layer {
+        pub name: String,
+        pub colour: Colour,
+        cells: HashSet<(usize, usize)>,
+    }
+
+    impl Layer {
+        pub fn new<I>(name: &str, colour: Colour, cells: I) -> Layer
+        where
+            I: IntoIterator<Item = (usize, usize)>,
+        {
+            Layer {
+                name: String::from(name),
+                colour,
+                cells: cells.into_iter().collect(),
+            }
+        }
+
+        pub fn contains(&self, x: usize, y: usize) -> bool {
+            self.cells.contains(&(x, y))
+        }
+    }
+
+    /// A grid of glyphs with layers painted over it. Later layers win where they overlap.
+    #[derive(Debug)]
+    pub struct Scene {
+        width: usize,
+        height: usize,
+        glyphs: Vec<Vec<char>>,
+        layers: Vec<Layer>,
+    }
+
+    impl Scene {
+        pub fn new(glyphs: Vec<Vec<char>>) -> Scene {
+            Scene {
+                width: glyphs.iter().map(|row| row.len()).max().unwrap_or(0),
+                height: glyphs.len(),
+                glyphs,
+                layers: vec![],
+            }
+        }
+
+        pub fn from_lines(lines: &[&str]) -> Scene {
+            Scene::new(lines.iter().map(|l| l.chars().collect()).collect())
+        }
+
+        pub fn with_layer(mut self, layer: Layer) -> Scene {
+            self.layers.push(layer);
+            self
+        }
+
+        pub fn width(&self) -> usize {
+            self.width
+        }
+
+        pub fn height(&self) -> usize {
+            self.height
+        }
+
+        fn glyph(&self, x: usize, y: usize) -> char {
+            self.glyphs[y].get(x).copied().unwrap_or(' ')
+        }
+
+        fn colour(&self, x: usize, y: usize) -> Option<Colour> {
+            self.layers
+                .iter()
+                .rev()
+                .find(|l| l.contains(x, y))
+                .map(|l| l.colour)
+        }
+
+        pub fn to_ansi(&self) -> String {
+            let mut out = String::new();
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let glyph = self.glyph(x, y);
+                    match self.colour(x, y) {
+                        Some(c) => {
+                            out.push_str(&format!("\x1b[{}m{}\x1b[0m", c.ansi_code(), glyph))
+                        }
+                        None => out.push(glyph),
+                    }
+                }
+                out.push('\n');
+            }
+
+            out
+        }
+
+        pub fn to_svg(&self) -> String {
+            let mut out = format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+                self.width * 10,
+                self.height * 10,
+                self.width,
+                self.height
+            );
+            out.push_str("<rect width=\"100%\" height=\"100%\" fill=\"#000000\"/>\n");
+
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    if let Some(c) = self.colour(x, y) {
+                        out.push_str(&format!(
+                            "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"{}\"/>\n",
+                            x,
+                            y,
+                            c.hex()
+                        ));
+                    }
+                }
+            }
+
+            out.push_str("<g font-family=\"monospace\" font-size=\"0.8\" text-anchor=\"middle\" fill=\"#ffffff\">\n");
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let glyph = self.glyph(x, y);
+                    if glyph.is_whitespace() {
+                        continue;
+                    }
+
+                    out.push_str(&format!(
+                        "<text x=\"{}.5\" y=\"{}.75\">{}</text>\n",
+                        x,
+                        y,
+                        escape_xml(glyph)
+                    ));
+                }
+            }
+            out.push_str("</g>\n</svg>\n");
+
+            out
+        }
+
+        /// Encode as a PNG with each cell drawn as a `cell_size` pixel square. Glyphs aren't drawn,
+        /// non-blank cells outside every layer are shaded grey.
+        pub fn to_png(&self, cell_size: usize) -> Result<Vec<u8>, AdventError> {
+            let width = self.width * cell_size;
+            let height = self.height * cell_size;
+
+            let mut pixels: Vec<u8> = Vec::with_capacity(width * height * 3);
+            for y in 0..self.height {
+                let row: Vec<u8> = (0..self.width)
+                    .flat_map(|x| {
+                        let rgb = match self.colour(x, y) {
+                            Some(c) => c.rgb(),
+                            None if is_blank(self.glyph(x, y)) => PNG_BACKGROUND,
+                            None => PNG_GLYPH,
+                        };
+                        rgb.repeat(cell_size)
+                    })
+                    .collect();
+
+                for _ in 0..cell_size {
+                    pixels.extend(&row);
+                }
+            }
+
+            let mut bytes: Vec<u8> = vec![];
+            let mut encoder = png::Encoder::new(&mut bytes, width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+
+            let mut writer = encoder
+                .write_header()
+                .map_err(|e| AdventError::GeneralError {
+                    message: format!("can't write png header: {}", e),
+                })?;
+            writer
+                .write_image_data(&pixels)
+                .map_err(|e| AdventError::GeneralError {
+                    message: format!("can't write png data: {}", e),
+                })?;
+            writer.finish().map_err(|e| AdventError::GeneralError {
+                message: format!("can't finish png: {}", e),
+            })?;
+
+            Ok(bytes)
+        }
+
+        /// Write to a file, picking SVG or PNG from the extension.
+        pub fn write(&self, path: &str) -> Result<(), AdventError> {
+            let bytes = if path.ends_with(".svg") {
+                self.to_svg().into_bytes()
+            } else if path.ends_with(".png") {
+                self.to_png(8)?
+            } else {
+                return Err(AdventError::GeneralError {
+                    message: String::from("can only render to .svg or .png"),
+                });
+            };
+
+            fs::write(path, bytes).map_err(|e| AdventError::IoError { inner: e })
+        }
+    }
+
+    fn is_blank(glyph: char) -> bool {
+        glyph == '.' || glyph.is_whitespace()
+    }
+
+    fn escape_xml(glyph: char) -> String {
+        match glyph {
+            '&' => String::from("&amp;"),
+            '<' => String::from("&lt;"),
+            '>' => String::from("&gt;"),
+            '"' => String::from("&quot;"),
+            c => c.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod render_tests {
+    use crate::render::{Colour, Layer, Scene};
+
+    fn example() -> Scene {
+        Scene::from_lines(&["#..", ".<.", "..#"])
+            .with_layer(Layer::new("diagonal", Colour::Red, [(0, 0), (1, 1)]))
+            .with_layer(Layer::new("corner", Colour::Green, [(1, 1), (2, 2)]))
+    }
+
+    #[test]
+    fn can_render_to_ansi() {
+        let ansi = example().to_ansi();
+
+        assert_eq!(
+            ansi,
+            "\x1b[31m#\x1b[0m..\n.\x1b[32m<\x1b[0m.\n..\x1b[32m#\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn can_render_to_svg() {
+        let svg = example().to_svg();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"30\" height=\"30\" viewBox=\"0 0 3 3\">"));
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"1\" height=\"1\" fill=\"#dc322f\"/>"));
+        assert!(svg.contains("<rect x=\"1\" y=\"1\" width=\"1\" height=\"1\" fill=\"#859900\"/>"));
+        assert!(svg.contains("<text x=\"1.5\" y=\"1.75\">&lt;</text>"));
+        assert_eq!(svg.matches("<rect x=").count(), 3);
+        assert_eq!(svg.matches("<text").count(), 9);
+    }
+
+    #[test]
+    fn can_render_to_png() {
+        let bytes = example().to_png(4).unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (12, 12));
+        assert_eq!(pixels[0..3], [220, 50, 47]);
+        assert_eq!(pixels[4 * 3..4 * 3 + 3], [0, 0, 0]);
+    }
+
+    #[test]
+    fn refuses_unknown_file_types() {
+        let result = example().write("grid.txt");
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            "can only render to .svg or .png"
+        );
+    }
+}
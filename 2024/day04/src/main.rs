@@ -1,10 +1,35 @@
 use common::core::AdventError;
 use common::file::read_lines;
+use common::render::{Colour, Layer, Scene};
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use word_search::{Search, Stencil, WordMatch, ALL, DIAGONAL, ORTHOGONAL};
 
 fn main() {
-    let matrix = parse_file().unwrap();
+    let matrix = match parse_file() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("{}", e.message());
+            std::process::exit(1)
+        }
+    };
+
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|a| a.as_str()) {
+        Some("bands") => return print_parts_in_bands(args.get(2)),
+        Some("find") => return print_words(&matrix, &args[2..]),
+        Some("stencil") => return print_stencils(&matrix, &args[2..]),
+        _ => (),
+    }
+
     println!("Part 1 Sum: {:?}", part_one(&matrix));
     println!("Part 2 Sum: {:?}", part_two(&matrix));
+
+    if let Some(path) = args.get(1) {
+        let words = Search::new(&ALL).find_words(&matrix, &["XMAS"]);
+        scene(&matrix, &words).write(path).unwrap();
+    }
 }
 
 fn parse_file() -> Result<Matrix, AdventError> {
@@ -12,8 +37,7 @@ fn parse_file() -> Result<Matrix, AdventError> {
 
     let data: Vec<Vec<char>> = lines.iter().map(|l| parse_line(l)).collect();
 
-    let size = data[0].len() as i32;
-    Ok(Matrix { data, size })
+    Matrix::new(data)
 }
 
 fn parse_line(line: &str) -> Vec<char> {
@@ -21,127 +45,520 @@ fn parse_line(line: &str) -> Vec<char> {
 }
 
 fn part_one(matrix: &Matrix) -> usize {
-    let mut matches = 0;
-    for y in 0..matrix.size {
-        for x in 0..matrix.size {
-            matches += search_from((x, y).into(), &matrix);
+    Search::new(&ALL).find_words(matrix, &["XMAS"]).len()
+}
+
+fn part_two(matrix: &Matrix) -> usize {
+    Search::new(&ALL)
+        .find_stencils(matrix, &x_mas().rotations())
+        .len()
+}
+
+fn x_mas() -> Stencil {
+    Stencil::parse(&["M.S", ".A.", "M.S"], '.')
+}
+
+/// `bands [rows]`: both parts again, reading the input a band of rows at a time.
+fn print_parts_in_bands(band_height: Option<&String>) {
+    let band_height = band_height.map_or(16, |h| h.parse().unwrap());
+    let search = Search::new(&ALL);
+
+    let part_one = search.find_words_in_bands(read_rows(), band_height, &["XMAS"]);
+    let part_two = search.find_stencils_in_bands(read_rows(), band_height, &x_mas().rotations());
+
+    match (part_one, part_two) {
+        (Ok(one), Ok(two)) => {
+            println!("Part 1 Sum: {:?}", one.len());
+            println!("Part 2 Sum: {:?}", two.len());
         }
+        (Err(e), _) | (_, Err(e)) => eprintln!("{}", e.message()),
     }
+}
 
-    matches
+fn read_rows() -> Box<dyn Iterator<Item = Result<String, AdventError>>> {
+    match File::open("./day04/input.txt") {
+        Ok(f) => Box::new(
+            BufReader::new(f)
+                .lines()
+                .map(|l| l.map_err(|e| AdventError::IoError { inner: e })),
+        ),
+        Err(e) => Box::new(std::iter::once(Err(AdventError::IoError { inner: e }))),
+    }
 }
 
-fn part_two(matrix: &Matrix) -> usize {
-    let mut matches = 0;
-    for y in 0..matrix.size {
-        for x in 0..matrix.size {
-            if search_from_two((x, y).into(), &matrix) {
-                matches += 1;
-            }
-        }
+/// `find [--orthogonal|--diagonal] [--wrap] [--palindromes-once] WORD...`
+fn print_words(matrix: &Matrix, args: &[String]) {
+    let directions: &[(i32, i32)] = if args.iter().any(|a| a == "--orthogonal") {
+        &ORTHOGONAL
+    } else if args.iter().any(|a| a == "--diagonal") {
+        &DIAGONAL
+    } else {
+        &ALL
+    };
+
+    let mut search = Search::new(directions);
+    if args.iter().any(|a| a == "--wrap") {
+        search = search.wrapping();
+    }
+    if args.iter().any(|a| a == "--palindromes-once") {
+        search = search.palindromes_once();
     }
 
-    matches
+    let words: Vec<&str> = args
+        .iter()
+        .filter(|a| !a.starts_with("--"))
+        .map(|a| a.as_str())
+        .collect();
+
+    let found = search.find_words(matrix, &words);
+    for word in found.iter() {
+        println!("{} at {}", word.word, format_cells(&word.cells));
+    }
+    println!("{} found", found.len());
 }
 
-fn search_from(start: Vector, matrix: &Matrix) -> usize {
-    const WORD: [char; 4] = ['X', 'M', 'A', 'S'];
+/// `stencil ROW/ROW/... [--reflect] [--wrap]`, where `.` matches anything, e.g. `M.S/.A./M.S`.
+fn print_stencils(matrix: &Matrix, args: &[String]) {
+    let Some(pattern) = args.iter().find(|a| !a.starts_with("--")) else {
+        eprintln!("usage: stencil ROW/ROW/... [--reflect] [--wrap]");
+        return;
+    };
+    let rows: Vec<&str> = pattern.split('/').collect();
+    let stencil = Stencil::parse(&rows, '.');
+
+    let stencils = match args.iter().any(|a| a == "--reflect") {
+        true => stencil.symmetries(),
+        false => stencil.rotations(),
+    };
+
+    let mut search = Search::new(&ALL);
+    if args.iter().any(|a| a == "--wrap") {
+        search = search.wrapping();
+    }
 
-    if matrix.get(&start) != Some(WORD[0]) {
-        return 0;
+    let found = search.find_stencils(matrix, &stencils);
+    for m in found.iter() {
+        println!(
+            "({}, {}): {}",
+            m.origin.x,
+            m.origin.y,
+            format_cells(&m.cells)
+        );
     }
+    println!("{} found", found.len());
+}
+
+fn format_cells(cells: &[Vector]) -> String {
+    cells
+        .iter()
+        .map(|c| format!("({}, {})", c.x, c.y))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn scene(matrix: &Matrix, words: &[WordMatch]) -> Scene {
+    let cells = words
+        .iter()
+        .flat_map(|w| w.cells.iter())
+        .map(|v| (v.x as usize, v.y as usize));
+
+    Scene::new(matrix.data.clone()).with_layer(Layer::new("found", Colour::Red, cells))
+}
+
+mod word_search {
+    use std::collections::HashSet;
+
+    use common::core::AdventError;
+
+    use super::{check_row, parse_line, Matrix, Vector};
+
+    pub const ORTHOGONAL: [(i32, i32); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+    pub const DIAGONAL: [(i32, i32); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
 
     #[rustfmt::skip]
-    let directions: [Vector; 8] = [
-        (-1, -1).into(), (0, -1).into(), ( 1, -1).into(),
-        (-1,  0).into(),                 ( 1,  0).into(),
-        (-1,  1).into(), (0,  1).into(), ( 1,  1).into(),
+    pub const ALL: [(i32, i32); 8] = [
+        (-1, -1), (0, -1), ( 1, -1),
+        (-1,  0),          ( 1,  0),
+        (-1,  1), (0,  1), ( 1,  1),
     ];
 
-    let candidates: Vec<Line> = directions
-        .iter()
-        .map(|d| Line {
-            direction: d.clone(),
-            position: start.clone(),
-        })
-        .collect();
+    /// A word and the cells it was read from, first letter first.
+    #[derive(Debug)]
+    pub struct WordMatch {
+        pub word: String,
+        pub cells: Vec<Vector>,
+    }
+
+    /// Where a stencil's top left corner was placed, and the cells its letters matched.
+    #[derive(Debug)]
+    pub struct StencilMatch {
+        pub origin: Vector,
+        pub cells: Vec<Vector>,
+    }
+
+    #[derive(Debug)]
+    pub struct Search {
+        directions: Vec<Vector>,
+        wrap: bool,
+        palindromes_once: bool,
+    }
+
+    impl Search {
+        pub fn new(directions: &[(i32, i32)]) -> Search {
+            Search {
+                directions: directions.iter().map(|&d| d.into()).collect(),
+                wrap: false,
+                palindromes_once: false,
+            }
+        }
+
+        /// Carry on from the opposite edge rather than stopping at the edge of the grid.
+        pub fn wrapping(mut self) -> Search {
+            self.wrap = true;
+            self
+        }
+
+        /// A word that reads the same both ways along a line is found from both ends. Keep
+        /// only the first of matches that cover the same cells.
+        pub fn palindromes_once(mut self) -> Search {
+            self.palindromes_once = true;
+            self
+        }
+
+        pub fn find_words(&self, matrix: &Matrix, words: &[&str]) -> Vec<WordMatch> {
+            let words: Vec<(&str, Vec<char>)> = words
+                .iter()
+                .filter(|w| !w.is_empty())
+                .map(|&w| (w, w.chars().collect()))
+                .collect();
+
+            let mut found = vec![];
+            let mut seen: HashSet<(&str, Vec<Vector>)> = HashSet::new();
+            for start in cells(matrix) {
+                for (word, letters) in words.iter() {
+                    for &direction in self.directions.iter() {
+                        let Some(cells) = self.follow(matrix, start, direction, letters) else {
+                            continue;
+                        };
+
+                        if self.palindromes_once {
+                            let mut key = cells.clone();
+                            key.sort();
+                            if !seen.insert((word, key)) {
+                                continue;
+                            }
+                        }
+
+                        found.push(WordMatch {
+                            word: String::from(*word),
+                            cells,
+                        });
+                    }
+                }
+            }
+
+            found
+        }
+
+        fn follow(
+            &self,
+            matrix: &Matrix,
+            start: Vector,
+            direction: Vector,
+            letters: &[char],
+        ) -> Option<Vec<Vector>> {
+            letters
+                .iter()
+                .enumerate()
+                .map(|(i, &letter)| {
+                    let point = self.locate(matrix, start + direction * i as i32)?;
+                    (matrix.get(&point) == Some(letter)).then_some(point)
+                })
+                .collect()
+        }
+
+        /// Every placement of every stencil whose letters all match.
+        pub fn find_stencils(&self, matrix: &Matrix, stencils: &[Stencil]) -> Vec<StencilMatch> {
+            let mut found = vec![];
+            for origin in cells(matrix) {
+                for stencil in stencils {
+                    let cells: Option<Vec<Vector>> = stencil
+                        .cells
+                        .iter()
+                        .map(|&(offset, letter)| {
+                            let point = self.locate(matrix, origin + offset)?;
+                            (matrix.get(&point) == Some(letter)).then_some(point)
+                        })
+                        .collect();
+
+                    if let Some(cells) = cells {
+                        found.push(StencilMatch { origin, cells });
+                    }
+                }
+            }
+
+            found
+        }
+
+        /// The same as `find_words`, holding only a band of rows and the few before it that a
+        /// word could reach back into.
+        pub fn find_words_in_bands(
+            &self,
+            rows: impl Iterator<Item = Result<String, AdventError>>,
+            band_height: usize,
+            words: &[&str],
+        ) -> Result<Vec<WordMatch>, AdventError> {
+            let steepest = self.directions.iter().map(|d| d.y.abs()).max().unwrap_or(0);
+            let longest = words.iter().map(|w| w.chars().count()).max().unwrap_or(0);
+            let reach = longest.saturating_sub(1) * steepest as usize + 1;
+
+            self.in_bands(rows, band_height, reach, |m| self.find_words(m, words))
+        }
+
+        pub fn find_stencils_in_bands(
+            &self,
+            rows: impl Iterator<Item = Result<String, AdventError>>,
+            band_height: usize,
+            stencils: &[Stencil],
+        ) -> Result<Vec<StencilMatch>, AdventError> {
+            let reach = stencils.iter().map(|s| s.height()).max().unwrap_or(0);
+
+            self.in_bands(rows, band_height, reach, |m| {
+                self.find_stencils(m, stencils)
+            })
+        }
 
-    let mut sum = 0;
-    for candidate in candidates {
-        let mut success = true;
-        for (i, &c) in WORD[1..].iter().enumerate() {
-            let point = candidate.position.clone() + (candidate.direction.clone() * (i as i32 + 1));
+        /// Searches each band along with the `reach - 1` rows before it, so anything up to
+        /// `reach` rows tall is wholly inside the band its bottom row is in, and is only
+        /// counted there.
+        fn in_bands<T: Placed>(
+            &self,
+            mut rows: impl Iterator<Item = Result<String, AdventError>>,
+            band_height: usize,
+            reach: usize,
+            find: impl Fn(&Matrix) -> Vec<T>,
+        ) -> Result<Vec<T>, AdventError> {
+            if self.wrap {
+                return Err(AdventError::GeneralError {
+                    message: String::from("can't wrap around a grid read in bands"),
+                });
+            }
+            if band_height == 0 {
+                return Err(AdventError::GeneralError {
+                    message: String::from("bands must be at least one row high"),
+                });
+            }
 
-            if matrix.get(&point) != Some(c) {
-                success = false;
-                break;
+            let mut found = vec![];
+            let mut window: Vec<Vec<char>> = vec![];
+            // Set by the very first row, as the window can be emptied between bands
+            let mut width: Option<usize> = None;
+            let mut first_row = 0;
+            loop {
+                let carried = window.len();
+                for row in rows.by_ref().take(band_height) {
+                    let row = parse_line(&row?);
+                    let width = *width.get_or_insert(row.len());
+                    check_row(first_row + window.len(), &row, width)?;
+                    window.push(row);
+                }
+                if window.len() == carried {
+                    break;
+                }
+
+                for mut m in find(&Matrix::new(window.clone())?) {
+                    if m.bottom() >= carried as i32 {
+                        m.move_down(first_row as i32);
+                        found.push(m);
+                    }
+                }
+
+                let dropped = window.len() - reach.saturating_sub(1).min(window.len());
+                window.drain(..dropped);
+                first_row += dropped;
             }
+
+            Ok(found)
         }
 
-        if success {
-            sum += 1;
+        fn locate(&self, matrix: &Matrix, point: Vector) -> Option<Vector> {
+            match self.wrap {
+                true => Some(Vector {
+                    x: point.x.rem_euclid(matrix.width),
+                    y: point.y.rem_euclid(matrix.height),
+                }),
+                false => matrix.get(&point).map(|_| point),
+            }
         }
     }
 
-    sum
-}
+    /// A match that can be moved from a band's rows to the whole grid's.
+    trait Placed {
+        fn bottom(&self) -> i32;
+        fn move_down(&mut self, rows: i32);
+    }
 
-fn search_from_two(start: Vector, matrix: &Matrix) -> bool {
-    const WORD: [char; 3] = ['M', 'A', 'S'];
+    impl Placed for WordMatch {
+        fn bottom(&self) -> i32 {
+            self.cells.iter().map(|c| c.y).max().unwrap_or(0)
+        }
 
-    if matrix.get(&start) != Some(WORD[1]) {
-        return false;
+        fn move_down(&mut self, rows: i32) {
+            for cell in self.cells.iter_mut() {
+                cell.y += rows;
+            }
+        }
     }
 
-    // /
-    let first: [Vector; 2] = [
-        start.clone() + (-1, 1).into(),
-        start.clone() + (1, -1).into(),
-    ];
-    // \
-    let second: [Vector; 2] = [
-        start.clone() + (-1, -1).into(),
-        start.clone() + (1, 1).into(),
-    ];
+    impl Placed for StencilMatch {
+        fn bottom(&self) -> i32 {
+            self.cells
+                .iter()
+                .map(|c| c.y)
+                .max()
+                .unwrap_or(self.origin.y)
+        }
+
+        fn move_down(&mut self, rows: i32) {
+            self.origin.y += rows;
+            for cell in self.cells.iter_mut() {
+                cell.y += rows;
+            }
+        }
+    }
+
+    fn cells(matrix: &Matrix) -> impl Iterator<Item = Vector> + '_ {
+        (0..matrix.height).flat_map(move |y| (0..matrix.width).map(move |x| (x, y).into()))
+    }
 
-    if (matrix.get(&first[0]) == Some(WORD[0]) && matrix.get(&first[1]) == Some(WORD[2])
-        || matrix.get(&first[0]) == Some(WORD[2]) && matrix.get(&first[1]) == Some(WORD[0]))
-        && (matrix.get(&second[0]) == Some(WORD[0]) && matrix.get(&second[1]) == Some(WORD[2])
-            || matrix.get(&second[0]) == Some(WORD[2]) && matrix.get(&second[1]) == Some(WORD[0]))
-    {
-        return true;
+    /// Letters at offsets from the top left of a pattern. Any other cell is a wildcard.
+    #[derive(PartialEq, Eq, Clone, Debug)]
+    pub struct Stencil {
+        cells: Vec<(Vector, char)>,
     }
 
-    false
+    impl Stencil {
+        pub fn parse(rows: &[&str], wildcard: char) -> Stencil {
+            let cells = rows
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .filter(|&(_, c)| c != wildcard)
+                        .map(move |(x, c)| ((x as i32, y as i32).into(), c))
+                })
+                .collect();
+
+            Stencil::normalised(cells)
+        }
+
+        /// Shift the letters so the top left is at 0,0, in a fixed order so equal shapes compare equal.
+        fn normalised(mut cells: Vec<(Vector, char)>) -> Stencil {
+            let min_x = cells.iter().map(|(v, _)| v.x).min().unwrap_or(0);
+            let min_y = cells.iter().map(|(v, _)| v.y).min().unwrap_or(0);
+
+            for (v, _) in cells.iter_mut() {
+                *v = *v + (-min_x, -min_y).into();
+            }
+            cells.sort();
+
+            Stencil { cells }
+        }
+
+        fn height(&self) -> usize {
+            self.cells
+                .iter()
+                .map(|(v, _)| v.y as usize + 1)
+                .max()
+                .unwrap_or(0)
+        }
+
+        fn map(&self, transform: impl Fn(Vector) -> Vector) -> Stencil {
+            Stencil::normalised(self.cells.iter().map(|&(v, c)| (transform(v), c)).collect())
+        }
+
+        /// The stencil turned through each quarter turn, without duplicates.
+        pub fn rotations(&self) -> Vec<Stencil> {
+            let mut rotations: Vec<Stencil> = vec![];
+            let mut stencil = self.clone();
+            for _ in 0..4 {
+                let next = stencil.map(|v| (-v.y, v.x).into());
+                if !rotations.contains(&stencil) {
+                    rotations.push(stencil);
+                }
+                stencil = next;
+            }
+
+            rotations
+        }
+
+        /// The rotations of the stencil and of its mirror image, without duplicates.
+        pub fn symmetries(&self) -> Vec<Stencil> {
+            let mut symmetries = self.rotations();
+            for stencil in self.map(|v| (-v.x, v.y).into()).rotations() {
+                if !symmetries.contains(&stencil) {
+                    symmetries.push(stencil);
+                }
+            }
+
+            symmetries
+        }
+    }
 }
 
 #[derive(Debug)]
 struct Matrix {
-    size: i32,
+    width: i32,
+    height: i32,
     data: Vec<Vec<char>>,
 }
 
 impl Matrix {
+    /// Every row must be as long as the first.
+    fn new(data: Vec<Vec<char>>) -> Result<Matrix, AdventError> {
+        let width = data.first().map_or(0, |r| r.len());
+        for (y, row) in data.iter().enumerate() {
+            check_row(y, row, width)?;
+        }
+
+        Ok(Matrix {
+            width: width as i32,
+            height: data.len() as i32,
+            data,
+        })
+    }
+
     fn get(&self, coordinate: &Vector) -> Option<char> {
         if coordinate.x < 0
             || coordinate.y < 0
-            || coordinate.x >= self.size
-            || coordinate.y >= self.size
+            || coordinate.x >= self.width
+            || coordinate.y >= self.height
         {
             return None;
         }
 
-        return Some(self.data[coordinate.y as usize][coordinate.x as usize]);
+        Some(self.data[coordinate.y as usize][coordinate.x as usize])
     }
 }
 
-#[derive(Clone, Debug)]
-struct Line {
-    position: Vector,
-    direction: Vector,
+/// Rows are numbered from 1 in the message, to match the line in the file.
+fn check_row(y: usize, row: &[char], width: usize) -> Result<(), AdventError> {
+    match row.len() == width {
+        true => Ok(()),
+        false => Err(AdventError::GeneralError {
+            message: format!(
+                "row {} has {} letters but the grid is {} wide",
+                y + 1,
+                row.len(),
+                width
+            ),
+        }),
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Debug)]
 struct Vector {
     x: i32,
     y: i32,
@@ -161,8 +578,8 @@ impl std::ops::Add for Vector {
 
     fn add(self, other: Vector) -> Vector {
         Vector {
-            x: i32::from(self.x) + other.x,
-            y: i32::from(self.y) + other.y,
+            x: self.x + other.x,
+            y: self.y + other.y,
         }
     }
 }
@@ -177,3 +594,129 @@ impl std::ops::Mul<i32> for Vector {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::word_search::{Search, Stencil, ALL};
+    use crate::{parse_line, Matrix, Vector};
+    use common::core::AdventError;
+
+    const GRID: [&str; 10] = [
+        "MMMSXXMASM",
+        "MSAMXMSMSA",
+        "AMXSXMAAMM",
+        "MSAMASMSMX",
+        "XMASAMXAMM",
+        "XXAMMXXAMA",
+        "SMSMSASXSS",
+        "SAXAMASAAA",
+        "MAMMMXMMMM",
+        "MXMXAXMASX",
+    ];
+
+    fn matrix(rows: &[&str]) -> Matrix {
+        Matrix::new(rows.iter().map(|r| parse_line(r)).collect()).unwrap()
+    }
+
+    fn rows<'a>(rows: &'a [&str]) -> impl Iterator<Item = Result<String, AdventError>> + 'a {
+        rows.iter().map(|r| Ok(String::from(*r)))
+    }
+
+    fn sorted(mut cells: Vec<Vec<Vector>>) -> Vec<Vec<Vector>> {
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn finds_a_palindrome_once_when_asked() {
+        let grid = matrix(&["RACECAR"]);
+        let both_ways = Search::new(&[(1, 0), (-1, 0)]);
+
+        let twice = both_ways.find_words(&grid, &["RACECAR"]);
+        let once = both_ways.palindromes_once().find_words(&grid, &["RACECAR"]);
+
+        assert_eq!(twice.len(), 2);
+        assert_eq!(once.len(), 1);
+        assert_eq!(once[0].cells, twice[0].cells);
+    }
+
+    #[test]
+    fn finds_a_word_that_wraps() {
+        let grid = matrix(&["SXMA", "...."]);
+        let across = Search::new(&[(1, 0)]);
+
+        assert!(across.find_words(&grid, &["XMAS"]).is_empty());
+
+        let found = across.wrapping().find_words(&grid, &["XMAS"]);
+        let cells: Vec<Vector> = [(1, 0), (2, 0), (3, 0), (0, 0)].map(Vector::from).to_vec();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].cells, cells);
+    }
+
+    #[test]
+    fn counts_symmetries_of_stencils() {
+        // Every letter differs, so each turn and reflection is a new stencil
+        let lopsided = Stencil::parse(&["AB", "C."], '.');
+        assert_eq!(lopsided.rotations().len(), 4);
+        assert_eq!(lopsided.symmetries().len(), 8);
+
+        // The mirror image of an X-MAS is one of its turns
+        let x_mas = Stencil::parse(&["M.S", ".A.", "M.S"], '.');
+        assert_eq!(x_mas.rotations().len(), 4);
+        assert_eq!(x_mas.symmetries().len(), 4);
+    }
+
+    #[test]
+    fn band_search_matches_whole_grid_search() {
+        let search = Search::new(&ALL);
+        let stencils = Stencil::parse(&["M.S", ".A.", "M.S"], '.').rotations();
+
+        let words = search.find_words(&matrix(&GRID), &["XMAS"]);
+        let shapes = search.find_stencils(&matrix(&GRID), &stencils);
+        assert_eq!(words.len(), 18);
+        assert_eq!(shapes.len(), 9);
+
+        for band_height in [1, 2, 3, 4, 7, 10, 20] {
+            let banded_words = search
+                .find_words_in_bands(rows(&GRID), band_height, &["XMAS"])
+                .unwrap();
+            let banded_shapes = search
+                .find_stencils_in_bands(rows(&GRID), band_height, &stencils)
+                .unwrap();
+
+            assert_eq!(
+                sorted(banded_words.into_iter().map(|m| m.cells).collect()),
+                sorted(words.iter().map(|m| m.cells.clone()).collect()),
+                "bands of {}",
+                band_height
+            );
+            assert_eq!(
+                sorted(banded_shapes.into_iter().map(|m| m.cells).collect()),
+                sorted(shapes.iter().map(|m| m.cells.clone()).collect()),
+                "bands of {}",
+                band_height
+            );
+        }
+    }
+
+    #[test]
+    fn band_search_reports_ragged_rows() {
+        // Reading only to the right reaches no other row, so each band starts empty
+        let across = Search::new(&[(1, 0)]);
+        let error = across
+            .find_words_in_bands(rows(&["ABC", "AB", "ABC"]), 1, &["AB"])
+            .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "row 2 has 2 letters but the grid is 3 wide"
+        );
+
+        let error = Search::new(&ALL)
+            .find_words_in_bands(rows(&["ABC", "ABC", "ABCD", "ABC"]), 2, &["AB"])
+            .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "row 3 has 4 letters but the grid is 3 wide"
+        );
+    }
+}